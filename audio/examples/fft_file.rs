@@ -0,0 +1,32 @@
+//!Prints the loudest frequency of a wav or flac file each tenth of a second, to check the analysis without a gpu
+
+use std::path::PathBuf;
+
+use audio::{analysis::DEFAULT_FFT_BINS, file::FileSource, AudioSource, FftAnalyzer};
+
+fn main() {
+    let path: PathBuf = std::env::args()
+        .nth(1)
+        .expect("Usage: fft_file <file.wav>")
+        .into();
+
+    let mut source = FileSource::open(&path).unwrap();
+    let mut analyzer = FftAnalyzer::default();
+    let len = FftAnalyzer::window_len(DEFAULT_FFT_BINS);
+
+    let steps = (source.duration() * 10.0) as u32;
+    for step in 0..steps {
+        let time = step as f32 / 10.0;
+        let waveform = source.window(time, len);
+        let image = analyzer.analyze(&waveform, DEFAULT_FFT_BINS);
+
+        let (bin, level) = image.values[..DEFAULT_FFT_BINS as usize]
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .unwrap();
+        let freq = bin as f32 * waveform.sample_rate as f32 / len as f32;
+
+        println!("{time:.1}s: {freq:.0}Hz at {level:.2}");
+    }
+}
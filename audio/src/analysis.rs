@@ -0,0 +1,112 @@
+use std::{f32::consts::PI, sync::Arc};
+
+use rustfft::{num_complex::Complex, Fft, FftPlanner};
+
+use crate::source::Waveform;
+
+///Samples in the audio image when the ISF input has no MAX
+pub const DEFAULT_WAVEFORM_SAMPLES: u32 = 512;
+///Frequency bins in the audioFFT image when the ISF input has no MAX
+pub const DEFAULT_FFT_BINS: u32 = 256;
+
+///A value per pixel, in rows from the bottom. Each row is a channel, the first channel at the bottom
+#[derive(Clone, Debug, PartialEq)]
+pub struct AudioImage {
+    pub width: u32,
+    pub height: u32,
+    pub values: Vec<f32>,
+}
+
+impl AudioImage {
+    ///The layout of an ISF audio input. Samples are mapped from -1..1 to 0..1
+    pub fn waveform(waveform: &Waveform, width: u32) -> Self {
+        let len = waveform.len();
+
+        let values = waveform
+            .channels
+            .iter()
+            .flat_map(|channel| {
+                (0..width as usize).map(move |x| match len {
+                    0 => 0.5,
+                    _ => channel[x * len / width as usize] * 0.5 + 0.5,
+                })
+            })
+            .collect();
+
+        Self {
+            width,
+            height: waveform.channels.len() as u32,
+            values,
+        }
+    }
+}
+
+///Makes the images of ISF audioFFT inputs
+pub struct FftAnalyzer {
+    planner: FftPlanner<f32>,
+    ///The last plan, as the size rarely changes
+    fft: Option<Arc<dyn Fft<f32>>>,
+    buffer: Vec<Complex<f32>>,
+}
+
+impl Default for FftAnalyzer {
+    fn default() -> Self {
+        Self {
+            planner: FftPlanner::new(),
+            fft: None,
+            buffer: vec![],
+        }
+    }
+}
+
+impl FftAnalyzer {
+    ///Samples needed for an image of bins wide
+    pub fn window_len(bins: u32) -> usize {
+        bins as usize * 2
+    }
+
+    ///Magnitudes of the lowest to highest frequency, across the width.
+    /// A full scale sine wave is about 1
+    pub fn analyze(&mut self, waveform: &Waveform, bins: u32) -> AudioImage {
+        let len = Self::window_len(bins);
+
+        let fft = match &self.fft {
+            Some(fft) if fft.len() == len => fft.clone(),
+            _ => {
+                let fft = self.planner.plan_fft_forward(len);
+                self.fft = Some(fft.clone());
+                fft
+            }
+        };
+
+        //a hann window halves the amplitude, so scale back up
+        let scale = 4.0 / len as f32;
+
+        let mut values = Vec::with_capacity(bins as usize * waveform.channels.len());
+        for channel in &waveform.channels {
+            self.buffer.clear();
+            self.buffer.extend((0..len).map(|i| {
+                let sample = channel.get(i).copied().unwrap_or_default();
+                Complex::new(sample * hann(i, len), 0.0)
+            }));
+
+            fft.process(&mut self.buffer);
+
+            values.extend(
+                self.buffer[..bins as usize]
+                    .iter()
+                    .map(|bin| (bin.norm() * scale).min(1.0)),
+            );
+        }
+
+        AudioImage {
+            width: bins,
+            height: waveform.channels.len() as u32,
+            values,
+        }
+    }
+}
+
+fn hann(i: usize, len: usize) -> f32 {
+    0.5 - 0.5 * (2.0 * PI * i as f32 / len as f32).cos()
+}
@@ -0,0 +1,116 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use cpal::{
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    Sample, SampleFormat, Stream, StreamConfig,
+};
+use thiserror::Error;
+
+use crate::source::{AudioSource, Waveform};
+
+///Seconds of input kept, more than any window needs
+const BUFFER_SECONDS: u32 = 1;
+
+#[derive(Error, Debug)]
+pub enum CaptureError {
+    #[error("No audio input device")]
+    NoDevice,
+    #[error("Could not get the input config {0}")]
+    Config(#[from] cpal::DefaultStreamConfigError),
+    #[error("Could not open the input stream {0}")]
+    Build(#[from] cpal::BuildStreamError),
+    #[error("Could not start the input stream {0}")]
+    Play(#[from] cpal::PlayStreamError),
+}
+
+///Interleaved samples, oldest first
+struct CaptureBuffer {
+    samples: VecDeque<f32>,
+    capacity: usize,
+}
+
+impl CaptureBuffer {
+    fn push(&mut self, samples: impl Iterator<Item = f32>) {
+        self.samples.extend(samples);
+        let excess = self.samples.len().saturating_sub(self.capacity);
+        self.samples.drain(..excess);
+    }
+}
+
+///Live input from the default recording device
+pub struct CaptureSource {
+    //stops recording when dropped
+    _stream: Stream,
+    buffer: Arc<Mutex<CaptureBuffer>>,
+    config: StreamConfig,
+}
+
+impl CaptureSource {
+    pub fn new() -> Result<Self, CaptureError> {
+        let device = cpal::default_host()
+            .default_input_device()
+            .ok_or(CaptureError::NoDevice)?;
+
+        let supported = device.default_input_config()?;
+        let format = supported.sample_format();
+        let config: StreamConfig = supported.into();
+
+        let capacity = (config.sample_rate.0 * BUFFER_SECONDS) as usize * config.channels as usize;
+        let buffer = Arc::new(Mutex::new(CaptureBuffer {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }));
+
+        let stream = match format {
+            SampleFormat::F32 => build_stream::<f32>(&device, &config, buffer.clone())?,
+            SampleFormat::I16 => build_stream::<i16>(&device, &config, buffer.clone())?,
+            SampleFormat::U16 => build_stream::<u16>(&device, &config, buffer.clone())?,
+        };
+        stream.play()?;
+
+        Ok(Self {
+            _stream: stream,
+            buffer,
+            config,
+        })
+    }
+}
+
+fn build_stream<T: Sample>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    buffer: Arc<Mutex<CaptureBuffer>>,
+) -> Result<Stream, cpal::BuildStreamError> {
+    device.build_input_stream(
+        config,
+        move |data: &[T], _| {
+            if let Ok(mut buffer) = buffer.lock() {
+                buffer.push(data.iter().map(Sample::to_f32));
+            }
+        },
+        |err| eprintln!("Audio capture error: {err}"),
+    )
+}
+
+impl AudioSource for CaptureSource {
+    fn window(&mut self, _time: f32, len: usize) -> Waveform {
+        let channels = self.config.channels as usize;
+        let sample_rate = self.config.sample_rate.0;
+
+        let buffer = match self.buffer.lock() {
+            Ok(buffer) => buffer,
+            Err(_) => return Waveform::silent(channels, len),
+        };
+
+        //pad the start with silence until enough has been recorded
+        let wanted = len * channels;
+        let available = buffer.samples.len().min(wanted);
+        let mut samples = vec![0.0; wanted - available];
+        samples.extend(buffer.samples.range(buffer.samples.len() - available..));
+
+        Waveform::deinterleave(sample_rate, channels, &samples)
+    }
+}
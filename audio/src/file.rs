@@ -0,0 +1,111 @@
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::source::{AudioSource, Waveform};
+
+#[derive(Error, Debug)]
+pub enum AudioFileError {
+    #[error("Wav error {0}")]
+    Wav(#[from] hound::Error),
+    #[error("Flac error {0}")]
+    Flac(#[from] claxon::Error),
+    #[error("Unsupported audio file {0:?}, use a .wav or .flac file")]
+    Unsupported(std::ffi::OsString),
+}
+
+///A decoded wav or flac file, looping along with the graph time
+pub struct FileSource {
+    samples: Waveform,
+}
+
+impl FileSource {
+    pub fn open(path: &Path) -> Result<Self, AudioFileError> {
+        let ext = path
+            .extension()
+            .map(|ext| ext.to_ascii_lowercase())
+            .unwrap_or_default();
+
+        let samples = match ext.to_str() {
+            Some("wav") => read_wav(path)?,
+            Some("flac") => read_flac(path)?,
+            _ => return Err(AudioFileError::Unsupported(path.as_os_str().to_owned())),
+        };
+
+        Ok(Self { samples })
+    }
+
+    ///Seconds until the file loops
+    pub fn duration(&self) -> f32 {
+        self.samples.len() as f32 / self.samples.sample_rate.max(1) as f32
+    }
+}
+
+fn read_wav(path: &Path) -> Result<Waveform, AudioFileError> {
+    let reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.into_samples::<f32>().collect::<Result<_, _>>()?,
+        hound::SampleFormat::Int => {
+            let scale = int_scale(spec.bits_per_sample as u32);
+            reader
+                .into_samples::<i32>()
+                .map(|sample| sample.map(|sample| sample as f32 * scale))
+                .collect::<Result<_, _>>()?
+        }
+    };
+
+    Ok(Waveform::deinterleave(
+        spec.sample_rate,
+        spec.channels as usize,
+        &samples,
+    ))
+}
+
+fn read_flac(path: &Path) -> Result<Waveform, AudioFileError> {
+    let mut reader = claxon::FlacReader::open(path)?;
+    let info = reader.streaminfo();
+    let scale = int_scale(info.bits_per_sample);
+
+    let samples: Vec<f32> = reader
+        .samples()
+        .map(|sample| sample.map(|sample| sample as f32 * scale))
+        .collect::<Result<_, _>>()?;
+
+    Ok(Waveform::deinterleave(
+        info.sample_rate,
+        info.channels as usize,
+        &samples,
+    ))
+}
+
+///Maps integer samples to -1..1
+fn int_scale(bits_per_sample: u32) -> f32 {
+    1.0 / (1u64 << (bits_per_sample.max(1) - 1)) as f32
+}
+
+impl AudioSource for FileSource {
+    fn window(&mut self, time: f32, len: usize) -> Waveform {
+        let total = self.samples.len();
+        if total == 0 {
+            return Waveform::silent(self.samples.channels.len().max(1), len);
+        }
+
+        let end = (time.max(0.0) as f64 * self.samples.sample_rate as f64) as usize;
+        //start a whole number of loops ahead so it never goes negative
+        let start = end % total + total * (len / total + 1) - len;
+
+        let channels = self
+            .samples
+            .channels
+            .iter()
+            .map(|channel| (start..start + len).map(|i| channel[i % total]).collect())
+            .collect();
+
+        Waveform {
+            sample_rate: self.samples.sample_rate,
+            channels,
+        }
+    }
+}
@@ -0,0 +1,11 @@
+//!Audio for the ISF audio and audioFFT inputs.
+//! A [source::AudioSource] gives the latest samples, which [analysis] turns into images in the ISF layout
+
+pub mod analysis;
+#[cfg(feature = "capture")]
+pub mod capture;
+pub mod file;
+pub mod source;
+
+pub use analysis::{AudioImage, FftAnalyzer};
+pub use source::{AudioSource, Waveform};
@@ -0,0 +1,52 @@
+///Samples between -1 and 1, a Vec per channel. Every channel is the same length
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Waveform {
+    pub sample_rate: u32,
+    pub channels: Vec<Vec<f32>>,
+}
+
+impl Waveform {
+    ///Silence, for when there is nothing to play
+    pub fn silent(channels: usize, len: usize) -> Self {
+        Self {
+            sample_rate: 0,
+            channels: vec![vec![0.0; len]; channels],
+        }
+    }
+
+    ///Samples in each channel
+    pub fn len(&self) -> usize {
+        self.channels.first().map(Vec::len).unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    ///Split interleaved samples into channels
+    pub fn deinterleave(sample_rate: u32, channel_count: usize, samples: &[f32]) -> Self {
+        let channel_count = channel_count.max(1);
+        let channels = (0..channel_count)
+            .map(|channel| {
+                samples
+                    .iter()
+                    .skip(channel)
+                    .step_by(channel_count)
+                    .copied()
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            sample_rate,
+            channels,
+        }
+    }
+}
+
+///Somewhere to get sound from, like a file or a microphone
+pub trait AudioSource {
+    ///The len samples of each channel before time, in seconds.
+    /// Live sources ignore the time and give the latest samples
+    fn window(&mut self, time: f32, len: usize) -> Waveform;
+}
@@ -0,0 +1,112 @@
+use std::path::PathBuf;
+
+use audio::{file::FileSource, AudioSource, FftAnalyzer};
+
+const SAMPLE_RATE: u32 = 1000;
+
+///A mono float wav in the temp folder, removed on drop
+struct TempWav(PathBuf);
+
+impl TempWav {
+    fn new(name: &str, samples: impl IntoIterator<Item = f32>) -> Self {
+        let path = std::env::temp_dir().join(format!("{name}_{}.wav", std::process::id()));
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: SAMPLE_RATE,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for sample in samples {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        Self(path)
+    }
+}
+
+impl Drop for TempWav {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+///Each sample holds its own index, so windows show where they were read from
+fn ramp(name: &str, len: usize) -> TempWav {
+    TempWav::new(name, (0..len).map(|i| i as f32 / len as f32))
+}
+
+fn indices(source: &mut FileSource, time: f32, len: usize, total: usize) -> Vec<usize> {
+    source.window(time, len).channels[0]
+        .iter()
+        .map(|sample| (sample * total as f32).round() as usize)
+        .collect()
+}
+
+#[test]
+fn window_ends_at_time() {
+    let wav = ramp("window_ends_at_time", 100);
+    let mut source = FileSource::open(&wav.0).unwrap();
+
+    assert_eq!(source.duration(), 0.1);
+    assert_eq!(indices(&mut source, 0.05, 4, 100), [46, 47, 48, 49]);
+}
+
+#[test]
+fn window_before_start_wraps_to_end() {
+    let wav = ramp("window_before_start", 100);
+    let mut source = FileSource::open(&wav.0).unwrap();
+
+    assert_eq!(indices(&mut source, 0.002, 5, 100), [97, 98, 99, 0, 1]);
+    assert_eq!(indices(&mut source, 0.0, 3, 100), [97, 98, 99]);
+}
+
+#[test]
+fn window_loops_after_duration() {
+    let wav = ramp("window_loops", 100);
+    let mut source = FileSource::open(&wav.0).unwrap();
+
+    assert_eq!(
+        indices(&mut source, 0.252, 4, 100),
+        indices(&mut source, 0.052, 4, 100)
+    );
+}
+
+#[test]
+fn window_longer_than_file_repeats_it() {
+    let wav = ramp("window_longer", 3);
+    let mut source = FileSource::open(&wav.0).unwrap();
+
+    assert_eq!(indices(&mut source, 0.0, 7, 3), [2, 0, 1, 2, 0, 1, 2]);
+}
+
+#[test]
+fn fft_peaks_at_sine_frequency() {
+    let bins = 64;
+    let len = FftAnalyzer::window_len(bins);
+    let peak_bin = 10;
+    let frequency = peak_bin as f32 * SAMPLE_RATE as f32 / len as f32;
+
+    let wav = TempWav::new(
+        "fft_peaks",
+        (0..SAMPLE_RATE).map(|i| {
+            (2.0 * std::f32::consts::PI * frequency * i as f32 / SAMPLE_RATE as f32).sin()
+        }),
+    );
+    let mut source = FileSource::open(&wav.0).unwrap();
+
+    let image = FftAnalyzer::default().analyze(&source.window(0.5, len), bins);
+    assert_eq!((image.width, image.height), (bins, 1));
+
+    let (loudest, magnitude) = image
+        .values
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .unwrap();
+
+    assert_eq!(loudest, peak_bin);
+    assert!((magnitude - 1.0).abs() < 0.05, "magnitude {magnitude}");
+}
@@ -0,0 +1,310 @@
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+///Time of the frame being rendered, the same for every node
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameTime {
+    ///Seconds since the clock started
+    pub time: f32,
+    ///Seconds since the last frame
+    pub delta: f32,
+    pub frame_index: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ClockMode {
+    ///Follow the wall clock
+    RealTime,
+    ///Advance by 1/fps every frame, however long the frame took
+    Fixed { fps: f32 },
+}
+
+impl Default for ClockMode {
+    fn default() -> Self {
+        ClockMode::RealTime
+    }
+}
+
+///Frame rate used to step the realtime clock while paused
+const STEP_FPS: f32 = 60.0;
+
+///Transport that decides the time of each frame
+#[derive(Debug)]
+pub struct Clock {
+    pub mode: ClockMode,
+    playing: bool,
+    ///Frames to advance while paused
+    steps: u32,
+    last_tick: Option<Instant>,
+    frame: FrameTime,
+    ///The current frame hasn't been rendered yet, so the next tick shows it instead of advancing
+    fresh: bool,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self {
+            mode: ClockMode::default(),
+            playing: true,
+            steps: 0,
+            last_tick: None,
+            frame: FrameTime::default(),
+            fresh: true,
+        }
+    }
+}
+
+impl Clock {
+    pub fn new(mode: ClockMode) -> Self {
+        Self {
+            mode,
+            ..Default::default()
+        }
+    }
+
+    ///Start the next frame. Returns the time to render it with
+    pub fn tick(&mut self) -> FrameTime {
+        let now = Instant::now();
+        let elapsed = self
+            .last_tick
+            .map(|last| (now - last).as_secs_f32())
+            .unwrap_or_default();
+        self.last_tick = Some(now);
+
+        let delta = match (self.playing, self.mode) {
+            (true, ClockMode::RealTime) => Some(elapsed),
+            (true, ClockMode::Fixed { fps }) => Some(1.0 / fps),
+            (false, _) if 0 < self.steps => {
+                self.steps -= 1;
+                Some(self.step_delta())
+            }
+            (false, _) => None,
+        };
+
+        //the first frame after a reset or seek is shown before advancing
+        let fresh = std::mem::take(&mut self.fresh);
+
+        self.frame = match delta.filter(|_| !fresh) {
+            Some(delta) => FrameTime {
+                time: self.frame.time + delta,
+                delta,
+                frame_index: self.frame.frame_index + 1,
+            },
+            //a paused frame is the last one again, with no time passing
+            None => FrameTime {
+                delta: 0.0,
+                ..self.frame
+            },
+        };
+
+        self.frame
+    }
+
+    ///The time of the last frame
+    pub fn frame(&self) -> FrameTime {
+        self.frame
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn play(&mut self) {
+        self.playing = true;
+        self.steps = 0;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn toggle(&mut self) {
+        if self.playing {
+            self.pause()
+        } else {
+            self.play()
+        }
+    }
+
+    ///Advance a single frame on the next tick, pausing if playing
+    pub fn step(&mut self) {
+        self.pause();
+        self.steps += 1;
+    }
+
+    ///Jump to a time. In fixed mode, the frame index matches the time
+    pub fn seek(&mut self, time: f32) {
+        let time = time.max(0.0);
+
+        self.frame = FrameTime {
+            time,
+            delta: 0.0,
+            frame_index: match self.mode {
+                ClockMode::Fixed { fps } => (time * fps).round() as u32,
+                ClockMode::RealTime => self.frame.frame_index,
+            },
+        };
+        self.fresh = true;
+    }
+
+    ///Go back to the start
+    pub fn reset(&mut self) {
+        self.frame = FrameTime::default();
+        self.fresh = true;
+    }
+
+    fn step_delta(&self) -> f32 {
+        match self.mode {
+            ClockMode::Fixed { fps } => 1.0 / fps,
+            ClockMode::RealTime => 1.0 / STEP_FPS,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///A power of two, so the times add up exactly
+    const FPS: f32 = 4.0;
+
+    fn fixed() -> Clock {
+        Clock::new(ClockMode::Fixed { fps: FPS })
+    }
+
+    fn frame(time: f32, delta: f32, frame_index: u32) -> FrameTime {
+        FrameTime {
+            time,
+            delta,
+            frame_index,
+        }
+    }
+
+    #[test]
+    fn fixed_tick_advances_by_one_frame() {
+        let mut clock = fixed();
+
+        assert_eq!(clock.tick(), frame(0.0, 0.0, 0));
+        assert_eq!(clock.tick(), frame(0.25, 0.25, 1));
+        assert_eq!(clock.tick(), frame(0.5, 0.25, 2));
+        assert_eq!(clock.frame(), frame(0.5, 0.25, 2));
+    }
+
+    #[test]
+    fn fixed_ticks_are_reproducible() {
+        let mut a = fixed();
+        let mut b = fixed();
+
+        let frames_a = (0..100).map(|_| a.tick()).collect::<Vec<_>>();
+        let frames_b = (0..100).map(|_| b.tick()).collect::<Vec<_>>();
+
+        assert_eq!(frames_a, frames_b);
+        assert_eq!(frames_a[99], frame(99.0 / FPS, 0.25, 99));
+    }
+
+    #[test]
+    fn paused_ticks_hold_the_frame() {
+        let mut clock = fixed();
+        clock.tick();
+        clock.tick();
+
+        clock.pause();
+        assert!(!clock.is_playing());
+        assert_eq!(clock.tick(), frame(0.25, 0.0, 1));
+        assert_eq!(clock.tick(), frame(0.25, 0.0, 1));
+
+        clock.play();
+        assert!(clock.is_playing());
+        assert_eq!(clock.tick(), frame(0.5, 0.25, 2));
+    }
+
+    #[test]
+    fn toggle_switches_between_play_and_pause() {
+        let mut clock = fixed();
+
+        clock.toggle();
+        assert!(!clock.is_playing());
+        clock.toggle();
+        assert!(clock.is_playing());
+    }
+
+    #[test]
+    fn step_advances_one_frame_while_paused() {
+        let mut clock = fixed();
+        clock.tick();
+
+        clock.step();
+        assert!(!clock.is_playing());
+        assert_eq!(clock.tick(), frame(0.25, 0.25, 1));
+        assert_eq!(clock.tick(), frame(0.25, 0.0, 1));
+
+        clock.step();
+        clock.step();
+        assert_eq!(clock.tick(), frame(0.5, 0.25, 2));
+        assert_eq!(clock.tick(), frame(0.75, 0.25, 3));
+        assert_eq!(clock.tick(), frame(0.75, 0.0, 3));
+    }
+
+    #[test]
+    fn realtime_step_uses_the_step_rate() {
+        let mut clock = Clock::new(ClockMode::RealTime);
+        clock.pause();
+        clock.tick();
+
+        clock.step();
+        assert_eq!(clock.tick(), frame(1.0 / STEP_FPS, 1.0 / STEP_FPS, 1));
+    }
+
+    #[test]
+    fn seek_shows_the_frame_at_that_time() {
+        let mut clock = fixed();
+        clock.tick();
+        clock.tick();
+
+        clock.seek(2.0);
+        assert_eq!(clock.frame(), frame(2.0, 0.0, 8));
+        assert_eq!(clock.tick(), frame(2.0, 0.0, 8));
+        assert_eq!(clock.tick(), frame(2.25, 0.25, 9));
+
+        clock.seek(-1.0);
+        assert_eq!(clock.tick(), frame(0.0, 0.0, 0));
+    }
+
+    #[test]
+    fn seek_while_paused_stays_paused() {
+        let mut clock = fixed();
+        clock.pause();
+
+        clock.seek(1.0);
+        assert_eq!(clock.tick(), frame(1.0, 0.0, 4));
+        assert_eq!(clock.tick(), frame(1.0, 0.0, 4));
+
+        clock.step();
+        assert_eq!(clock.tick(), frame(1.25, 0.25, 5));
+    }
+
+    #[test]
+    fn realtime_seek_keeps_the_frame_index() {
+        let mut clock = Clock::new(ClockMode::RealTime);
+        clock.pause();
+        clock.tick();
+        clock.step();
+        clock.tick();
+
+        clock.seek(3.0);
+        assert_eq!(clock.tick(), frame(3.0, 0.0, 1));
+    }
+
+    #[test]
+    fn reset_goes_back_to_the_first_frame() {
+        let mut clock = fixed();
+        for _ in 0..5 {
+            clock.tick();
+        }
+
+        clock.reset();
+        assert_eq!(clock.tick(), frame(0.0, 0.0, 0));
+        assert_eq!(clock.tick(), frame(0.25, 0.25, 1));
+    }
+}
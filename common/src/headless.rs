@@ -0,0 +1,38 @@
+use glium::{
+    glutin::{self, dpi::PhysicalSize},
+    HeadlessRenderer, IncompatibleOpenGl,
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum HeadlessError {
+    #[error("Could not create GL context {0}")]
+    Creation(#[from] glutin::CreationError),
+
+    #[error("Incompatible GL context {0}")]
+    Incompatible(#[from] IncompatibleOpenGl),
+}
+
+///GL facade without a window, for tools, servers and tests that render a graph offscreen.
+/// Uses OSMesa on linux so it works without a display server or gpu (needs libOSMesa),
+/// otherwise a hidden glutin context
+pub fn new_headless_facade(res: (u32, u32)) -> Result<HeadlessRenderer, HeadlessError> {
+    let size = PhysicalSize::new(res.0, res.1);
+    let builder = glutin::ContextBuilder::new()
+        .with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGl, (3, 3)))
+        .with_gl_profile(glutin::GlProfile::Core);
+
+    #[cfg(target_os = "linux")]
+    let context = {
+        use glutin::platform::unix::HeadlessContextExt;
+        builder.build_osmesa(size)?
+    };
+
+    #[cfg(not(target_os = "linux"))]
+    let context = {
+        let event_loop = glutin::event_loop::EventLoop::new();
+        builder.build_headless(&event_loop, size)?
+    };
+
+    Ok(HeadlessRenderer::new(context)?)
+}
@@ -1,4 +1,6 @@
+pub mod clock;
 pub mod files;
+pub mod headless;
 pub mod texture;
 pub mod tree;
 pub mod tuple;
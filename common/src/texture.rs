@@ -1,24 +1,52 @@
 use glium::{
     backend::Facade,
-    texture::{DepthTexture2d, SrgbTexture2d},
+    texture::{DepthTexture2d, SrgbTexture2d, UncompressedFloatFormat},
     Texture2d,
 };
+use serde::{Deserialize, Serialize};
 
 pub const DEFAULT_RES: (u32, u32) = (1920, 1080);
 
+///Pixel format of a color texture
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Serialize, Deserialize)]
+pub enum TextureFormat {
+    #[default]
+    Rgba8,
+    Rgba16F,
+    Rgba32F,
+}
+
+impl TextureFormat {
+    pub const ALL: [TextureFormat; 3] = [
+        TextureFormat::Rgba8,
+        TextureFormat::Rgba16F,
+        TextureFormat::Rgba32F,
+    ];
+
+    pub fn uncompressed(&self) -> UncompressedFloatFormat {
+        match self {
+            TextureFormat::Rgba8 => UncompressedFloatFormat::U8U8U8U8,
+            TextureFormat::Rgba16F => UncompressedFloatFormat::F16F16F16F16,
+            TextureFormat::Rgba32F => UncompressedFloatFormat::F32F32F32F32,
+        }
+    }
+}
+
 const NO_MIPMAP: glium::texture::MipmapsOption = glium::texture::MipmapsOption::NoMipmap;
 
 pub fn new_texture_2d(
+    facade: &impl Facade,
+    res: (u32, u32),
+) -> Result<Texture2d, glium::texture::TextureCreationError> {
+    new_texture_2d_with_format(facade, res, TextureFormat::Rgba8)
+}
+
+pub fn new_texture_2d_with_format(
     facade: &impl Facade,
     (width, height): (u32, u32),
+    format: TextureFormat,
 ) -> Result<Texture2d, glium::texture::TextureCreationError> {
-    Texture2d::empty_with_format(
-        facade,
-        glium::texture::UncompressedFloatFormat::U8U8U8U8,
-        NO_MIPMAP,
-        width,
-        height,
-    )
+    Texture2d::empty_with_format(facade, format.uncompressed(), NO_MIPMAP, width, height)
 }
 
 pub fn new_depth_texture_2d(
@@ -0,0 +1,47 @@
+use audio::{capture::CaptureSource, file::FileSource};
+use egui::Ui;
+use graph::AudioTextures;
+
+///Picks the sound that ISF audio inputs react to
+pub fn draw_audio(audio: &mut AudioTextures, ui: &mut Ui) {
+    ui.horizontal(|ui| {
+        ui.label(format!("Audio: {}", audio.source_name().unwrap_or("None")));
+
+        if ui
+            .button("File")
+            .on_hover_text("Play a wav or flac file along with the clock")
+            .clicked()
+        {
+            let path = native_dialog::FileDialog::new()
+                .add_filter("Audio", &["wav", "flac"])
+                .show_open_single_file();
+
+            match path {
+                Ok(Some(path)) => match FileSource::open(&path) {
+                    Ok(source) => {
+                        let name = path.file_name().unwrap_or_default().to_string_lossy();
+                        audio.set_source(name, Box::new(source));
+                    }
+                    Err(err) => eprintln!("Failed to open {path:?}: {err}"),
+                },
+                Ok(None) => {}
+                Err(err) => eprintln!("Failed to pick audio file: {err:?}"),
+            }
+        }
+
+        if ui
+            .button("Live")
+            .on_hover_text("Listen to the default recording device")
+            .clicked()
+        {
+            match CaptureSource::new() {
+                Ok(source) => audio.set_source("Live", Box::new(source)),
+                Err(err) => eprintln!("Failed to start audio capture: {err}"),
+            }
+        }
+
+        if audio.source_name().is_some() && ui.button("Stop").clicked() {
+            audio.clear_source();
+        }
+    });
+}
@@ -0,0 +1,200 @@
+use egui::{Pos2, Vec2};
+use egui_node_graph::NodeId;
+use graph::{animation::DataUpdater, GetTemplate, GraphChangeEvent, UniqueNodeName};
+use serde::{Deserialize, Serialize};
+
+use super::def::*;
+
+///Nodes copied out of a graph.
+/// Stored on the system clipboard as json, so they can be pasted into another project
+#[derive(Serialize, Deserialize)]
+pub struct NodeClipboard {
+    nodes: Vec<CopiedNode>,
+    connections: Vec<CopiedConnection>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CopiedNode {
+    name: String,
+    data: graph::NodeData,
+    ///Position relative to the top left of the copied nodes
+    offset: Vec2,
+    values: Vec<(String, UiValue)>,
+    animations: Vec<(String, DataUpdater)>,
+}
+
+///Connection between two copied nodes, by index and param name
+#[derive(Serialize, Deserialize)]
+struct CopiedConnection {
+    output: (usize, String),
+    input: (usize, String),
+}
+
+impl NodeClipboard {
+    pub fn copy(editor: &GraphEditorState, graph_state: &GraphState, node_ids: &[NodeId]) -> Self {
+        let graph = &editor.graph;
+        let node_ids: Vec<_> = node_ids
+            .iter()
+            .cloned()
+            .filter(|node_id| graph.nodes.contains_key(*node_id))
+            .collect();
+
+        let top_left = node_ids
+            .iter()
+            .filter_map(|node_id| editor.node_positions.get(*node_id))
+            .fold(Pos2::new(f32::INFINITY, f32::INFINITY), |min, pos| min.min(*pos));
+
+        let nodes = node_ids
+            .iter()
+            .map(|node_id| {
+                let node = &graph[*node_id];
+
+                let name = graph_state
+                    .node_names
+                    .get(*node_id)
+                    .map(|name| name.name.clone())
+                    .unwrap_or_else(|| node.user_data.template().get_name().to_string());
+
+                let values = node
+                    .inputs
+                    .iter()
+                    .map(|(name, input_id)| (name.clone(), graph[*input_id].value.clone()))
+                    .collect();
+
+                let animations = graph_state
+                    .animator
+                    .animations
+                    .iter()
+                    .filter(|((id, _), _)| id == node_id)
+                    .map(|((_, param), updater)| (param.clone(), updater.clone()))
+                    .collect();
+
+                CopiedNode {
+                    name,
+                    data: node.user_data.inner.clone(),
+                    offset: editor.node_positions[*node_id] - top_left,
+                    values,
+                    animations,
+                }
+            })
+            .collect();
+
+        let index_of = |node_id| node_ids.iter().position(|id| *id == node_id);
+
+        //only keep connections inside of the copied nodes
+        let connections = graph
+            .connections
+            .iter()
+            .filter_map(|(input_id, output_id)| {
+                let input = &graph[input_id];
+                let output = &graph[*output_id];
+
+                let input_name = graph[input.node]
+                    .inputs
+                    .iter()
+                    .find(|(_, id)| *id == input_id)?
+                    .0
+                    .clone();
+                let output_name = graph[output.node]
+                    .outputs
+                    .iter()
+                    .find(|(_, id)| id == output_id)?
+                    .0
+                    .clone();
+
+                Some(CopiedConnection {
+                    output: (index_of(output.node)?, output_name),
+                    input: (index_of(input.node)?, input_name),
+                })
+            })
+            .collect();
+
+        Self { nodes, connections }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub fn from_json(text: &str) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(text)?)
+    }
+
+    ///Create the copied nodes with new ids and unique names, with their top left at pos.
+    /// Returns the new nodes and the changes to apply
+    pub fn paste(
+        self,
+        editor: &mut GraphEditorState,
+        graph_state: &mut GraphState,
+        pos: Pos2,
+    ) -> (Vec<NodeId>, Vec<GraphChangeEvent>) {
+        let mut changes = vec![];
+
+        let new_ids: Vec<NodeId> = self
+            .nodes
+            .into_iter()
+            .map(|copied| {
+                //after the highest number in use, so names never collide
+                let num = graph_state
+                    .node_names
+                    .values()
+                    .filter(|name| name.name == copied.name)
+                    .map(|name| name.num + 1)
+                    .max()
+                    .unwrap_or(0);
+                let unique_name = UniqueNodeName::new(copied.name, num);
+
+                let template = copied.data.template.clone();
+                let node_id = editor.graph.add_node(
+                    unique_name.to_string(),
+                    copied.data.into(),
+                    |graph, node_id| template.build_node(graph, node_id),
+                );
+
+                for (name, value) in copied.values {
+                    if let Ok(input_id) = editor.graph[node_id].get_input(&name) {
+                        editor.graph[input_id].value = value;
+                    }
+                }
+
+                for (param, updater) in copied.animations {
+                    graph_state
+                        .animator
+                        .animations
+                        .insert((node_id, param), updater);
+                }
+
+                graph_state.node_names.insert(node_id, unique_name);
+                editor.node_positions.insert(node_id, pos + copied.offset);
+                editor.node_order.push(node_id);
+
+                changes.push(GraphChangeEvent::CreatedNode(node_id));
+
+                node_id
+            })
+            .collect();
+
+        for CopiedConnection { output, input } in self.connections {
+            let output_id = new_ids
+                .get(output.0)
+                .and_then(|node_id| editor.graph[*node_id].get_output(&output.1).ok());
+            let input_id = new_ids
+                .get(input.0)
+                .and_then(|node_id| editor.graph[*node_id].get_input(&input.1).ok());
+
+            if let (Some(output_id), Some(input_id)) = (output_id, input_id) {
+                editor.graph.add_connection(output_id, input_id);
+                changes.push(GraphChangeEvent::Connected {
+                    output_id,
+                    input_id,
+                });
+            }
+        }
+
+        (new_ids, changes)
+    }
+}
@@ -4,7 +4,7 @@ use egui_node_graph::{NodeId, NodeTemplateIter, NodeTemplateTrait};
 use glium::uniforms::UniformValue;
 use graph::{
     def::{AsUniformOptional, GetUiValue},
-    GetTemplate, NodeError,
+    GetNodeFlags, GetOutputSettings, GetTemplate, NodeError, NodeFlags, OutputSettings,
 };
 use serde::{Deserialize, Serialize};
 use std::{cell::RefCell, rc::Weak, time::Duration};
@@ -57,6 +57,24 @@ impl GetTemplate for UiNodeData {
     }
 }
 
+impl GetOutputSettings for UiNodeData {
+    delegate! {
+        to self.inner {
+            fn output_settings(&self) -> &OutputSettings;
+            fn output_settings_mut(&mut self) -> &mut OutputSettings;
+        }
+    }
+}
+
+impl GetNodeFlags for UiNodeData {
+    delegate! {
+        to self.inner {
+            fn flags(&self) -> &NodeFlags;
+            fn flags_mut(&mut self) -> &mut NodeFlags;
+        }
+    }
+}
+
 #[derive(Default, Clone, Debug, Serialize, Deserialize)]
 #[repr(transparent)]
 pub struct UiValue(pub graph::def::UiValue);
@@ -85,7 +103,15 @@ impl AsUniformOptional for UiValue {
 }
 
 #[derive(Clone, Debug)]
-pub struct CustomGraphResponse;
+pub enum CustomGraphResponse {
+    ///Edit the graph inside of a subgraph node
+    EnterSubgraph(NodeId),
+    SetOutputSettings(NodeId, OutputSettings),
+    ///Bypass or freeze was toggled in the node header
+    SetFlags(NodeId, NodeFlags),
+    ///A parameter was edited in the ui
+    ValueChanged(NodeId),
+}
 impl egui_node_graph::UserResponseTrait for CustomGraphResponse {}
 
 pub type GraphResponse = egui_node_graph::GraphResponse<CustomGraphResponse, UiNodeData>;
@@ -95,7 +121,7 @@ pub type GraphState = graph::GraphState;
 pub type Node = egui_node_graph::Node<UiNodeData>;
 pub type Graph = egui_node_graph::Graph<UiNodeData, ConnectionType, UiValue>;
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct NodeType(pub graph::NodeType);
 
 impl NodeTemplateTrait for NodeType {
@@ -122,22 +148,7 @@ impl NodeTemplateTrait for NodeType {
         _user_state: &mut Self::UserState,
         node_id: NodeId,
     ) {
-        for input in self.0.get_input_types() {
-            if let Some(kind) = input.kind() {
-                graph.add_input_param(
-                    node_id,
-                    input.name,
-                    input.ty,
-                    input.value.into(),
-                    kind,
-                    true,
-                );
-            }
-        }
-
-        for output in self.0.get_output_types() {
-            graph.add_output_param(node_id, output.name, output.ty);
-        }
+        self.0.build_node(graph, node_id);
     }
 }
 
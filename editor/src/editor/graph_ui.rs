@@ -1,6 +1,7 @@
 // use crate::textures::TextureManager;
 // use crate::util::MappableTuple;
 use crate::widgets::debug::debug_options;
+use common::clock::Clock;
 use common::tree::LeafIndex;
 use common::tuple::MappableTuple;
 use egui::{Color32, Rect, RichText, Vec2};
@@ -16,14 +17,21 @@ use try_utils::some;
 
 // use crate::textures::UiTexture;
 
-use graph::{GetTemplate, GraphChangeEvent, TextureManager, UniqueNodeName};
+use graph::{
+    GetNodeFlags, GetOutputSettings, GetTemplate, GraphChangeEvent, SubgraphDef, TextureManager,
+    UniqueNodeName,
+};
 
 use persistence::{PersistentState, WindowState};
 
 use super::animation_ui::draw_dataupdater;
+use super::audio_ui::draw_audio;
+use super::clipboard::NodeClipboard;
 use super::def::{NodeResponse, *};
+use super::history::{GraphSnapshot, UndoHistory};
 use super::node_textures::NodeUiTextures;
 use super::node_tree_ui::TreeState;
+use super::transport_ui::draw_transport;
 
 pub struct GraphUi {
     editor: GraphEditorState,
@@ -32,16 +40,39 @@ pub struct GraphUi {
     node_textures: NodeUiTextures,
     state: GraphUiState,
     texture_manager: TextureManager,
+    history: UndoHistory,
+    ///Shared by the graph and every subgraph
+    clock: Clock,
+
+    ///Graphs containing the subgraph being edited, outermost first
+    parents: Vec<ParentGraph>,
+}
+
+///A graph that is hidden while one of its subgraph nodes is being edited
+struct ParentGraph {
+    node_id: NodeId,
+    editor: GraphEditorState,
+    graph_state: GraphState,
+    node_textures: NodeUiTextures,
+    history: UndoHistory,
 }
 
 pub enum RenderRequest {
     Leaf(LeafIndex),
 }
 
+pub enum SubgraphNavigation {
+    Enter(NodeId),
+    Exit,
+}
+
 #[derive(Default)]
 pub struct GraphUiResult {
     pub graph_changes: Vec<GraphChangeEvent>,
     pub render_requests: Vec<RenderRequest>,
+    pub navigation: Option<SubgraphNavigation>,
+    ///Nodes were moved or values were changed
+    pub edited: bool,
 }
 
 impl GraphUiResult {
@@ -57,19 +88,27 @@ impl GraphUiResult {
                 .into_iter()
                 .chain(other.render_requests.into_iter())
                 .collect_vec(),
+            navigation: self.navigation.or(other.navigation),
+            edited: self.edited || other.edited,
         }
     }
 }
 
 impl Default for GraphUi {
     fn default() -> Self {
+        let editor = GraphEditorState::new(1.0);
+        let graph_state = GraphState::default();
+
         Self {
-            editor: GraphEditorState::new(1.0),
+            history: UndoHistory::new(GraphSnapshot::new(&editor, &graph_state)),
+            editor,
             texture_manager: TextureManager::default(),
-            graph_state: GraphState::default(),
+            graph_state,
             tree: TreeState::default(),
             node_textures: NodeUiTextures::default(),
             state: GraphUiState::default(),
+            clock: Clock::default(),
+            parents: Vec::new(),
         }
     }
 }
@@ -80,11 +119,40 @@ enum GraphUiAction {
     ToggleAddNodeModal,
     Escape,
     ToggleViewState,
+    Undo,
+    Redo,
+    Copy,
+    Paste(String),
+    Duplicate,
 }
 
 impl GraphUiAction {
     fn from_keyboard_pressed(ctx: &egui::Context) -> Option<Self> {
-        if ctx.input().key_pressed(egui::Key::H) {
+        //text fields have their own undo and clipboard
+        let editing_text = ctx.wants_keyboard_input();
+        let command = !editing_text && ctx.input().modifiers.command;
+        let undo_pressed = command && ctx.input().key_pressed(egui::Key::Z);
+
+        let clipboard_action = ctx
+            .input()
+            .events
+            .iter()
+            .find_map(|event| match event {
+                egui::Event::Copy => Some(Self::Copy),
+                egui::Event::Paste(text) => Some(Self::Paste(text.clone())),
+                _ => None,
+            })
+            .filter(|_| !editing_text);
+
+        if clipboard_action.is_some() {
+            clipboard_action
+        } else if command && ctx.input().key_pressed(egui::Key::D) {
+            Some(Self::Duplicate)
+        } else if undo_pressed && ctx.input().modifiers.shift {
+            Some(Self::Redo)
+        } else if undo_pressed {
+            Some(Self::Undo)
+        } else if ctx.input().key_pressed(egui::Key::H) {
             Some(Self::Home)
         } else if ctx.input().key_pressed(egui::Key::Tab) {
             Some(Self::ToggleAddNodeModal)
@@ -104,7 +172,7 @@ impl GraphUi {
         facade: &impl Facade,
         egui_glium: &mut EguiGlium,
     ) -> anyhow::Result<Self> {
-        Ok(Self {
+        let mut graph_ui = Self {
             node_textures: NodeUiTextures::new_from_graph(&mut state.graph, facade, egui_glium),
             graph_state: GraphState::from_persistent_state(
                 &mut state.graph,
@@ -120,13 +188,33 @@ impl GraphUi {
                 ..Default::default()
             },
             ..Default::default()
-        })
+        };
+
+        graph_ui.history = UndoHistory::new(graph_ui.snapshot());
+
+        Ok(graph_ui)
+    }
+
+    fn snapshot(&self) -> GraphSnapshot {
+        GraphSnapshot::new(&self.editor, &self.graph_state)
     }
 
     pub fn to_persistent(
-        self,
+        mut self,
         extras: Option<WindowState>,
     ) -> PersistentState<UiNodeData, ConnectionType, UiValue> {
+        //save open subgraphs into their nodes
+        while !self.parents.is_empty() {
+            if let Err(err) = self.exit_subgraph() {
+                eprintln!("Failed to save subgraph, discarding changes.\nERR({err:?})");
+
+                let root = self.parents.swap_remove(0);
+                self.editor = root.editor;
+                self.graph_state = root.graph_state;
+                self.parents.clear();
+            }
+        }
+
         PersistentState {
             graph: self.editor.graph,
             node_positions: self.editor.node_positions,
@@ -138,7 +226,10 @@ impl GraphUi {
     }
 
     pub fn update(&mut self, facade: &impl Facade) {
-        let mut resp = self.graph_state.update(&mut self.editor.graph, facade);
+        let time = self.clock.frame();
+        let mut resp = self
+            .graph_state
+            .update(&mut self.editor.graph, &time, facade);
 
         for (node_id, node) in &mut self.editor.graph.nodes {
             node.user_data.update_error = resp.remove(node_id).map(|e| e.into());
@@ -149,8 +240,33 @@ impl GraphUi {
         // }
     }
 
+    ///Change the project resolution to match the window
+    pub fn resize(&mut self, facade: &impl Facade, res: (u32, u32)) {
+        //minimised
+        if res.0 == 0 || res.1 == 0 {
+            return;
+        }
+
+        self.graph_state.processor.resize(
+            &self.editor.graph,
+            facade,
+            &mut self.texture_manager,
+            res,
+        );
+
+        for parent in &mut self.parents {
+            parent.graph_state.processor.resize(
+                &parent.editor.graph,
+                facade,
+                &mut self.texture_manager,
+                res,
+            );
+        }
+    }
+
     pub fn process_frame(&mut self, display: &Display, egui_glium: &mut EguiGlium) {
         let mut frame = display.draw();
+        let frame_time = self.clock.tick();
 
         let action = GraphUiAction::from_keyboard_pressed(&egui_glium.egui_ctx);
         if action == Some(GraphUiAction::ToggleViewState) {
@@ -159,6 +275,14 @@ impl GraphUi {
             && self.state.view_state == ViewState::Output
         {
             self.state.view_state = ViewState::Graph;
+        } else if action == Some(GraphUiAction::Undo) {
+            if let Some(snapshot) = self.history.undo() {
+                self.restore(snapshot, display, egui_glium);
+            }
+        } else if action == Some(GraphUiAction::Redo) {
+            if let Some(snapshot) = self.history.redo() {
+                self.restore(snapshot, display, egui_glium);
+            }
         }
 
         const MONO_COLOR: f32 = 0.1;
@@ -179,41 +303,31 @@ impl GraphUi {
                 if let Some(response) = graph_response {
                     render_requests = response.render_requests;
 
+                    let edited = response.edited || !response.graph_changes.is_empty();
+
                     for change in response.graph_changes {
-                        use graph::GraphUpdateListener;
+                        self.apply_graph_change(change, display, egui_glium);
+                    }
 
-                        let resp = self.graph_state.processor.graph_event(
-                            &mut self.editor.graph,
-                            display,
-                            change,
-                        );
+                    self.record_history(edited, egui_glium);
 
-                        match resp {
-                            Err(err) => match change {
-                                GraphChangeEvent::CreatedNode(node_id) => {
-                                    self.editor.graph.nodes[node_id].user_data.create_error =
-                                        Some(err.into());
-                                }
-                                // GraphChangeEvent::Connected {
-                                //     output_id,
-                                //     input_id,
-                                // } => {
-                                //     self.editor.graph.remove_connection(output_id, input_id);
-                                // }
-                                _ => {}
-                            },
-                            Ok(_) => match change {
-                                GraphChangeEvent::CreatedNode(node_id) => {
-                                    let node = self.editor.graph.nodes.get_mut(node_id).unwrap();
-                                    self.node_textures.add(display, egui_glium, node);
-                                }
-                                GraphChangeEvent::DestroyedNode(node_id) => {
-                                    self.node_textures.remove(node_id);
-                                }
-
-                                _ => resp.expect("Unexpected graph update failure"),
-                            },
+                    let navigated = match response.navigation {
+                        Some(SubgraphNavigation::Enter(node_id)) => {
+                            self.enter_subgraph(node_id, display, egui_glium)
                         }
+                        Some(SubgraphNavigation::Exit) => self.exit_subgraph().map(|changes| {
+                            for change in changes {
+                                self.apply_graph_change(change, display, egui_glium);
+                            }
+
+                            //the edits inside the subgraph are a single step
+                            self.record_history(true, egui_glium);
+                        }),
+                        None => Ok(()),
+                    };
+
+                    if let Err(err) = navigated {
+                        eprintln!("Failed to change subgraph.\nERR({err:?})");
                     }
                 }
             }
@@ -251,23 +365,47 @@ impl GraphUi {
             &mut self.editor.graph,
             display,
             &mut self.texture_manager,
+            &frame_time,
             |node_id, tex: &Texture2d| {
                 let surface = tex.as_surface();
 
-                if let Some((preview_target_node_id, _)) = render_previews_connection {
-                    if node_id == preview_target_node_id {
-                        surface.fill(
-                            &preview_tex_input.as_surface(),
-                            glium::uniforms::MagnifySamplerFilter::Linear,
-                        );
-                    }
-                }
-
                 self.node_textures
                     .copy_surface(display, egui_glium, node_id, &surface);
             },
         );
 
+        //use the cooked output, the node may not have rendered this frame
+        if let Some((preview_target_node_id, param_id)) = render_previews_connection {
+            //show the output that was dragged from
+            //the first output is the main one
+            let output_name = match param_id {
+                AnyParameterId::Output(output_id) => self.editor.graph[preview_target_node_id]
+                    .outputs
+                    .iter()
+                    .skip(1)
+                    .find(|(_, id)| *id == output_id)
+                    .map(|(name, _)| name.as_str()),
+                AnyParameterId::Input(_) => None,
+            };
+
+            let preview_output = self
+                .graph_state
+                .processor
+                .outputs(preview_target_node_id)
+                .and_then(|outputs| match output_name {
+                    Some(name) => outputs.get(name),
+                    None => Some(&outputs.main),
+                })
+                .and_then(|output| output.texture());
+
+            if let Some(tex) = preview_output {
+                tex.as_surface().fill(
+                    &preview_tex_input.as_surface(),
+                    glium::uniforms::MagnifySamplerFilter::Linear,
+                );
+            }
+        }
+
         for (node_id, data) in self.editor.graph.nodes.iter_mut() {
             data.user_data.render_error = outputs.errors.remove(node_id);
 
@@ -289,6 +427,7 @@ impl GraphUi {
                 display,
                 egui_glium,
                 &mut self.texture_manager,
+                &frame_time,
                 Some(preview_tex_input.as_ref()),
             );
         }
@@ -317,6 +456,263 @@ impl GraphUi {
         frame.finish().unwrap();
     }
 
+    ///Update data that stays aligned with the graph
+    fn apply_graph_change(
+        &mut self,
+        change: GraphChangeEvent,
+        display: &Display,
+        egui_glium: &mut EguiGlium,
+    ) {
+        use graph::GraphUpdateListener;
+
+        let resp = self
+            .graph_state
+            .processor
+            .graph_event(&mut self.editor.graph, display, change);
+
+        match resp {
+            Err(err) => match change {
+                GraphChangeEvent::CreatedNode(node_id) => {
+                    self.editor.graph.nodes[node_id].user_data.create_error = Some(err.into());
+                }
+                // GraphChangeEvent::Connected {
+                //     output_id,
+                //     input_id,
+                // } => {
+                //     self.editor.graph.remove_connection(output_id, input_id);
+                // }
+                _ => {}
+            },
+            Ok(_) => match change {
+                GraphChangeEvent::CreatedNode(node_id) => {
+                    let node = self.editor.graph.nodes.get_mut(node_id).unwrap();
+                    self.node_textures.add(display, egui_glium, node);
+                }
+                GraphChangeEvent::DestroyedNode(node_id) => {
+                    self.node_textures.remove(node_id);
+                }
+
+                _ => resp.expect("Unexpected graph update failure"),
+            },
+        }
+    }
+
+    ///Copy the selected nodes to the system clipboard
+    fn copy_selection(&self, ctx: &egui::Context) {
+        let clipboard =
+            NodeClipboard::copy(&self.editor, &self.graph_state, &self.editor.selected_nodes);
+
+        if clipboard.is_empty() {
+            return;
+        }
+
+        match clipboard.to_json() {
+            Ok(json) => ctx.output().copied_text = json,
+            Err(err) => eprintln!("Failed to copy nodes.\nERR({err:?})"),
+        }
+    }
+
+    ///Add copied nodes with their top left at pos and select them
+    fn paste(&mut self, clipboard: NodeClipboard, pos: egui::Pos2) -> Vec<GraphChangeEvent> {
+        let (new_nodes, changes) = clipboard.paste(&mut self.editor, &mut self.graph_state, pos);
+        self.editor.selected_nodes = new_nodes;
+
+        changes
+    }
+
+    ///Add the current graph to the undo history if it was edited this frame
+    fn record_history(&mut self, edited: bool, egui_glium: &EguiGlium) {
+        let pointer_down = egui_glium.egui_ctx.input().pointer.any_down();
+
+        if edited {
+            let snapshot = self.snapshot();
+            self.history.record(snapshot, pointer_down);
+        } else {
+            self.history.end_edit(pointer_down);
+        }
+    }
+
+    ///Replace the graph with a state from the undo history.
+    /// Only the nodes and connections that differ are sent to the processor
+    fn restore(&mut self, snapshot: GraphSnapshot, display: &Display, egui_glium: &mut EguiGlium) {
+        let removed = snapshot.removed_events(&self.editor.graph);
+        let added = snapshot.added_events(&self.editor.graph);
+
+        for change in removed {
+            self.apply_graph_change(change, display, egui_glium);
+        }
+
+        self.editor.graph = snapshot.graph;
+        self.editor.node_positions = snapshot.node_positions;
+        self.editor.node_order = snapshot.node_order;
+        self.editor.connection_in_progress = None;
+        self.graph_state.node_names = snapshot.node_names;
+        self.graph_state.animator = snapshot.animator;
+
+        for change in added {
+            self.apply_graph_change(change, display, egui_glium);
+        }
+
+        //output settings are not part of the cooked inputs
+        for node_id in self.editor.graph.nodes.keys() {
+            self.graph_state.processor.mark_dirty(node_id);
+        }
+    }
+
+    ///Edit the graph inside of a subgraph node
+    fn enter_subgraph(
+        &mut self,
+        node_id: NodeId,
+        facade: &impl Facade,
+        egui_glium: &mut EguiGlium,
+    ) -> anyhow::Result<()> {
+        let def = match self.editor.graph[node_id].user_data.template() {
+            graph::NodeType::Subgraph { def, .. } => def.clone(),
+            _ => return Ok(()),
+        };
+
+        let mut inner_graph: Graph = persistence::convert(&def.graph)?;
+
+        let mut graph_state = GraphState::from_persistent_state(
+            &mut inner_graph,
+            def.node_names,
+            def.animator,
+            facade,
+        )?;
+        graph_state.exposed = Some(def.exposed);
+
+        let node_textures = NodeUiTextures::new_from_graph(&mut inner_graph, facade, egui_glium);
+
+        let editor = GraphEditorState {
+            node_order: inner_graph.nodes.keys().collect_vec(),
+            graph: inner_graph,
+            node_positions: def.node_positions,
+            ..Default::default()
+        };
+
+        let history = UndoHistory::new(GraphSnapshot::new(&editor, &graph_state));
+
+        self.parents.push(ParentGraph {
+            node_id,
+            editor: std::mem::replace(&mut self.editor, editor),
+            graph_state: std::mem::replace(&mut self.graph_state, graph_state),
+            node_textures: std::mem::replace(&mut self.node_textures, node_textures),
+            history: std::mem::replace(&mut self.history, history),
+        });
+
+        Ok(())
+    }
+
+    ///Save the subgraph being edited into its node and go back to the containing graph
+    fn exit_subgraph(&mut self) -> anyhow::Result<Vec<GraphChangeEvent>> {
+        if self.parents.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let inner_graph = persistence::convert(&self.editor.graph)?;
+
+        let parent = self.parents.pop().unwrap();
+        let editor = std::mem::replace(&mut self.editor, parent.editor);
+        let graph_state = std::mem::replace(&mut self.graph_state, parent.graph_state);
+        self.node_textures = parent.node_textures;
+        self.history = parent.history;
+
+        if let graph::NodeType::Subgraph { def, .. } =
+            self.editor.graph.nodes[parent.node_id].user_data.template_mut()
+        {
+            *def = SubgraphDef {
+                graph: inner_graph,
+                node_names: graph_state.node_names,
+                node_positions: editor.node_positions,
+                animator: graph_state.animator,
+                exposed: graph_state.exposed.unwrap_or_default(),
+                version: def.version + 1,
+            };
+        }
+
+        Ok(self.sync_node_params(parent.node_id))
+    }
+
+    ///Match the params of a node to its template, keeping connections to params that still exist
+    fn sync_node_params(&mut self, node_id: NodeId) -> Vec<GraphChangeEvent> {
+        let graph = &mut self.editor.graph;
+        let template = graph[node_id].user_data.template().clone();
+        let mut changes = vec![];
+
+        let input_types = template.get_input_types();
+        let stale_inputs = graph[node_id]
+            .inputs
+            .iter()
+            .filter(|(name, input_id)| {
+                !input_types
+                    .iter()
+                    .any(|input| &input.name == name && input.ty == graph[*input_id].typ)
+            })
+            .map(|(_, input_id)| *input_id)
+            .collect_vec();
+
+        for input_id in stale_inputs {
+            if let Some(output_id) = graph.connection(input_id) {
+                changes.push(GraphChangeEvent::Disconnected {
+                    output_id,
+                    input_id,
+                });
+            }
+
+            graph.remove_input_param(input_id);
+        }
+
+        for input in input_types {
+            if graph[node_id].get_input(&input.name).is_err() {
+                if let Some(kind) = input.kind() {
+                    graph.add_input_param(
+                        node_id,
+                        input.name,
+                        input.ty,
+                        input.value.into(),
+                        kind,
+                        true,
+                    );
+                }
+            }
+        }
+
+        let output_types = template.get_output_types();
+        let stale_outputs = graph[node_id]
+            .outputs
+            .iter()
+            .filter(|(name, output_id)| {
+                !output_types
+                    .iter()
+                    .any(|output| &output.name == name && output.ty == graph[*output_id].typ)
+            })
+            .map(|(_, output_id)| *output_id)
+            .collect_vec();
+
+        for output_id in stale_outputs {
+            for (input_id, _) in graph
+                .connections
+                .iter()
+                .filter(|(_, connected)| **connected == output_id)
+            {
+                changes.push(GraphChangeEvent::Disconnected {
+                    output_id,
+                    input_id,
+                });
+            }
+
+            graph.remove_output_param(output_id);
+        }
+
+        for output in output_types {
+            if graph[node_id].get_output(&output.name).is_err() {
+                graph.add_output_param(node_id, output.name, output.ty);
+            }
+        }
+
+        changes
+    }
+
     pub fn add_node(
         &mut self,
         node_type: &NodeType,
@@ -330,7 +726,7 @@ impl GraphUi {
             .graph
             .nodes
             .iter()
-            .filter(|(_, n)| n.user_data.template() == &node_type.0)
+            .filter(|(_, n)| n.user_data.template().same_template(&node_type.0))
             .count();
 
         let unique_name = UniqueNodeName::new(
@@ -387,7 +783,47 @@ impl GraphUi {
             dbg!(action);
         }
 
-        egui::TopBottomPanel::top("Titlebar").show(ctx, |_| {});
+        let pos = self.interaction_pos_on_graph(ctx);
+        let clipboard_changes = match &action {
+            Some(GraphUiAction::Copy) => {
+                self.copy_selection(ctx);
+                vec![]
+            }
+            //other text may be on the clipboard
+            Some(GraphUiAction::Paste(text)) => match NodeClipboard::from_json(text) {
+                Ok(clipboard) => self.paste(clipboard, pos),
+                Err(_) => vec![],
+            },
+            Some(GraphUiAction::Duplicate) => {
+                let clipboard = NodeClipboard::copy(
+                    &self.editor,
+                    &self.graph_state,
+                    &self.editor.selected_nodes,
+                );
+                self.paste(clipboard, pos)
+            }
+            _ => vec![],
+        };
+
+        let mut exit_subgraph = false;
+
+        egui::TopBottomPanel::top("Titlebar").show(ctx, |ui| {
+            draw_transport(&mut self.clock, ui);
+            draw_audio(&mut self.texture_manager.audio, ui);
+
+            if !self.parents.is_empty() {
+                ui.horizontal(|ui| {
+                    exit_subgraph = ui.button("⬅ Exit subgraph").clicked();
+
+                    let path = self
+                        .parents
+                        .iter()
+                        .map(|parent| parent.editor.graph[parent.node_id].label.as_str())
+                        .join(" / ");
+                    ui.label(path);
+                });
+            }
+        });
 
         if !self.graph_state.animator.animations.is_empty() {
             self.draw_animators(ctx);
@@ -445,11 +881,49 @@ impl GraphUi {
 
         let extra_responses = self.draw_node_selector_window(action, ctx);
 
+        for resp in &node_responses {
+            if let NodeResponse::User(CustomGraphResponse::SetOutputSettings(node_id, settings)) =
+                resp
+            {
+                *self.editor.graph[*node_id].user_data.output_settings_mut() = *settings;
+                self.graph_state.processor.mark_dirty(*node_id);
+            }
+
+            if let NodeResponse::User(CustomGraphResponse::SetFlags(node_id, flags)) = resp {
+                *self.editor.graph[*node_id].user_data.flags_mut() = *flags;
+                self.graph_state.processor.mark_dirty(*node_id);
+            }
+        }
+
+        let edited = node_responses.iter().any(|resp| {
+            matches!(
+                resp,
+                NodeResponse::MoveNode { .. }
+                    | NodeResponse::User(CustomGraphResponse::ValueChanged(..))
+                    | NodeResponse::User(CustomGraphResponse::SetOutputSettings(..))
+                    | NodeResponse::User(CustomGraphResponse::SetFlags(..))
+            )
+        });
+
+        let entered_subgraph = node_responses.iter().find_map(|resp| match resp {
+            NodeResponse::User(CustomGraphResponse::EnterSubgraph(node_id)) => {
+                Some(SubgraphNavigation::Enter(*node_id))
+            }
+            _ => None,
+        });
+
         GraphUiResult {
             graph_changes: node_responses
                 .iter()
                 .filter_map(GraphChangeEvent::from_response)
+                .chain(clipboard_changes)
                 .collect_vec(),
+            navigation: if exit_subgraph {
+                Some(SubgraphNavigation::Exit)
+            } else {
+                entered_subgraph
+            },
+            edited,
             ..Default::default()
         }
         .union(extra_responses)
@@ -525,6 +999,8 @@ impl GraphUi {
                         .collect()
                 })
                 .unwrap_or_default(),
+            navigation: None,
+            edited: false,
         }
     }
 
@@ -0,0 +1,142 @@
+use egui::Pos2;
+use egui_node_graph::{InputId, NodeId, OutputId};
+use graph::{Animator, GraphChangeEvent, UniqueNodeName};
+use slotmap::SecondaryMap;
+
+use super::def::*;
+
+const MAX_HISTORY: usize = 100;
+
+///Everything that is put back by undo and redo
+#[derive(Clone)]
+pub struct GraphSnapshot {
+    pub graph: Graph,
+    pub node_positions: SecondaryMap<NodeId, Pos2>,
+    pub node_order: Vec<NodeId>,
+    pub node_names: SecondaryMap<NodeId, UniqueNodeName>,
+    pub animator: Animator,
+}
+
+impl GraphSnapshot {
+    pub fn new(editor: &GraphEditorState, graph_state: &GraphState) -> Self {
+        Self {
+            graph: editor.graph.clone(),
+            node_positions: editor.node_positions.clone(),
+            node_order: editor.node_order.clone(),
+            node_names: graph_state.node_names.clone(),
+            animator: graph_state.animator.clone(),
+        }
+    }
+
+    ///Events that remove the nodes and connections of graph that are not in this snapshot.
+    /// Apply them before the snapshot replaces graph
+    pub fn removed_events(&self, graph: &Graph) -> Vec<GraphChangeEvent> {
+        let disconnected = missing_connections(graph, &self.graph).map(|(output_id, input_id)| {
+            GraphChangeEvent::Disconnected {
+                output_id,
+                input_id,
+            }
+        });
+        let destroyed = missing_nodes(graph, &self.graph).map(GraphChangeEvent::DestroyedNode);
+
+        disconnected.chain(destroyed).collect()
+    }
+
+    ///Events that add the nodes and connections of this snapshot that are not in graph.
+    /// Apply them after the snapshot replaces graph
+    pub fn added_events(&self, graph: &Graph) -> Vec<GraphChangeEvent> {
+        let created = missing_nodes(&self.graph, graph).map(GraphChangeEvent::CreatedNode);
+        let connected = missing_connections(&self.graph, graph).map(|(output_id, input_id)| {
+            GraphChangeEvent::Connected {
+                output_id,
+                input_id,
+            }
+        });
+
+        created.chain(connected).collect()
+    }
+}
+
+///Nodes of graph that are not in other
+fn missing_nodes<'a>(graph: &'a Graph, other: &'a Graph) -> impl Iterator<Item = NodeId> + 'a {
+    graph
+        .nodes
+        .keys()
+        .filter(|node_id| !other.nodes.contains_key(*node_id))
+}
+
+///Connections of graph that are not in other
+fn missing_connections<'a>(
+    graph: &'a Graph,
+    other: &'a Graph,
+) -> impl Iterator<Item = (OutputId, InputId)> + 'a {
+    graph
+        .connections
+        .iter()
+        .filter(|(input_id, output_id)| other.connections.get(*input_id) != Some(*output_id))
+        .map(|(input_id, output_id)| (*output_id, input_id))
+}
+
+///Undo and redo stacks for a single graph.
+/// Node and slot ids are kept in the snapshots, so restoring one keeps the same ids
+pub struct UndoHistory {
+    ///State after the last recorded edit
+    current: GraphSnapshot,
+    undo: Vec<GraphSnapshot>,
+    redo: Vec<GraphSnapshot>,
+
+    ///A drag is still editing, so the next edits are merged into the last one
+    merging: bool,
+}
+
+impl UndoHistory {
+    pub fn new(current: GraphSnapshot) -> Self {
+        Self {
+            current,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            merging: false,
+        }
+    }
+
+    ///Call after the graph was edited.
+    /// Edits made while the pointer stays down become a single step
+    pub fn record(&mut self, snapshot: GraphSnapshot, pointer_down: bool) {
+        let previous = std::mem::replace(&mut self.current, snapshot);
+
+        if !self.merging {
+            self.undo.push(previous);
+            if MAX_HISTORY < self.undo.len() {
+                self.undo.remove(0);
+            }
+            self.redo.clear();
+        }
+
+        self.merging = pointer_down;
+    }
+
+    ///Call on frames without an edit, so the next edit starts a new step
+    pub fn end_edit(&mut self, pointer_down: bool) {
+        self.merging &= pointer_down;
+    }
+
+    ///The state to restore to undo the last edit
+    pub fn undo(&mut self) -> Option<GraphSnapshot> {
+        let previous = self.undo.pop()?;
+        let current = std::mem::replace(&mut self.current, previous.clone());
+        self.redo.push(current);
+        self.merging = false;
+
+        Some(previous)
+    }
+
+    ///The state to restore to redo the last undone edit
+    pub fn redo(&mut self) -> Option<GraphSnapshot> {
+        let next = self.redo.pop()?;
+        let current = std::mem::replace(&mut self.current, next.clone());
+        self.undo.push(current);
+        self.merging = false;
+
+        Some(next)
+    }
+}
@@ -1,8 +1,12 @@
 mod animation_ui;
+mod audio_ui;
+mod clipboard;
+mod history;
 mod node_textures;
 mod node_tree_ui;
 mod node_ui;
 mod prop_ui;
+mod transport_ui;
 
 mod def;
 pub mod graph_ui;
@@ -1,5 +1,6 @@
 use std::fmt::Display;
 
+use common::clock::FrameTime;
 use common::tree::{LeafIndex, Tree};
 use egui::{Color32, RichText, Stroke, Widget};
 use egui_glium::EguiGlium;
@@ -8,7 +9,10 @@ use glium::{
     uniforms::{AsUniformValue, Uniforms},
     Surface,
 };
-use graph::{connections::InputDef, def::AsUniformOptional, NodeShader, TextureManager};
+use graph::{
+    connections::InputDef, def::AsUniformOptional, NodeShader, OutputSettings, TextureManager,
+};
+use persistence::config::Config;
 
 use serde::Serialize;
 use slotmap::SecondaryMap;
@@ -68,26 +72,40 @@ pub struct TreeState {
     filter: FilterState,
     pub tree: Tree<String, graph::NodeType>,
     pub renders: SecondaryMap<LeafIndex, RenderNodeItem>,
+    config: Config,
 }
 
 impl Default for TreeState {
     fn default() -> Self {
-        let tree = graph::NodeType::templates();
-
-        let mut renders = SecondaryMap::default();
-
-        for (leaf_id, node_ty) in &tree.leaves {
-            renders.insert(leaf_id, RenderNodeItem::new(node_ty.clone()));
-        }
+        let config = Config::load_from_default_path();
+        let (tree, renders) = load_templates(&config);
 
         Self {
             tree,
             filter: FilterState::default(),
             renders,
+            config,
         }
     }
 }
 
+fn load_templates(
+    config: &Config,
+) -> (
+    Tree<String, graph::NodeType>,
+    SecondaryMap<LeafIndex, RenderNodeItem>,
+) {
+    let tree = graph::NodeType::templates(&config.isf_dirs);
+
+    let mut renders = SecondaryMap::default();
+
+    for (leaf_id, node_ty) in &tree.leaves {
+        renders.insert(leaf_id, RenderNodeItem::new(node_ty.clone()));
+    }
+
+    (tree, renders)
+}
+
 pub struct TreeDrawResult {
     pub clicked: Option<LeafIndex>,
     pub in_view: Vec<LeafIndex>,
@@ -117,6 +135,15 @@ impl TreeState {
                 .clicked();
         });
 
+        if self.isf_dirs_ui(ui) {
+            if let Err(err) = self.config.write_to_default_path() {
+                eprintln!("Failed to save config: {err:?}");
+            }
+
+            (self.tree, self.renders) = load_templates(&self.config);
+            search_changed = true;
+        }
+
         let open_state = if !search_changed {
             None
         } else if self.filter.text.is_empty() {
@@ -169,6 +196,44 @@ impl TreeState {
             in_view: leaves_in_view,
         }
     }
+
+    ///Extra folders to search for ISF shaders. Returns true if they changed
+    fn isf_dirs_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+
+        ui.collapsing("ISF folders", |ui| {
+            let mut removed = None;
+
+            for (i, dir) in self.config.isf_dirs.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.small_button("x").on_hover_text("Remove").clicked() {
+                        removed = Some(i);
+                    }
+                    ui.label(dir.to_string_lossy());
+                });
+            }
+
+            if let Some(i) = removed {
+                self.config.isf_dirs.remove(i);
+                changed = true;
+            }
+
+            if ui.button("Add folder").clicked() {
+                let new_dir = native_dialog::FileDialog::new().show_open_single_dir();
+
+                match new_dir {
+                    Ok(Some(dir)) if !self.config.isf_dirs.contains(&dir) => {
+                        self.config.isf_dirs.push(dir);
+                        changed = true;
+                    }
+                    Ok(_) => {}
+                    Err(err) => eprintln!("Failed to pick folder: {err:?}"),
+                }
+            }
+        });
+
+        changed
+    }
 }
 
 pub struct RenderNodeItem {
@@ -218,6 +283,7 @@ impl RenderNodeItem {
         facade: &impl Facade,
         egui_glium: &mut EguiGlium,
         texture_manager: &mut TextureManager,
+        time: &FrameTime,
         input_tex: Option<&glium::Texture2d>,
     ) {
         if self.instance.is_none() {
@@ -238,8 +304,16 @@ impl RenderNodeItem {
                 inputs: &inputs,
             };
 
-            if let Ok(output) = shader.render(facade, texture_manager, uniforms) {
-                img.copy_from(facade, &output.as_surface());
+            if let Ok(output) = shader.render(
+                facade,
+                texture_manager,
+                &OutputSettings::default(),
+                time,
+                uniforms,
+            ) {
+                if let Some(texture) = output.main.texture() {
+                    img.copy_from(facade, &texture.as_surface());
+                }
             } else {
                 img.framebuffer(facade)
                     .unwrap()
@@ -1,8 +1,11 @@
 use std::{cell::RefCell, rc::Weak};
 
-use egui::{Color32, Label, Response, RichText, Sense, Stroke, Ui};
+use common::texture::{TextureFormat, DEFAULT_RES};
+use egui::{Color32, DragValue, Label, Response, RichText, Sense, Slider, Stroke, Ui};
 use egui_node_graph::{Graph, NodeDataTrait, NodeId};
 
+use graph::{GetNodeFlags, GetOutputSettings, GetTemplate, OutputRes, OutputSettings};
+
 use super::{def::*, ui_texture::UiTexture};
 
 fn draw_error(ui: &mut egui::Ui, name: &str, error: &Option<graph::NodeError>) {
@@ -78,6 +81,42 @@ impl NodeDataTrait for UiNodeData {
     type DataType = ConnectionType;
     type ValueType = UiValue;
 
+    fn titlebar_ui(
+        &self,
+        ui: &mut egui::Ui,
+        node_id: NodeId,
+        _graph: &Graph<Self, Self::DataType, Self::ValueType>,
+        _state: &mut Self::UserState,
+    ) -> Vec<egui_node_graph::NodeResponse<Self::Response, Self>>
+    where
+        Self::Response: egui_node_graph::UserResponseTrait,
+    {
+        let mut flags = *self.flags();
+
+        if ui
+            .selectable_label(flags.bypass, "B")
+            .on_hover_text("Bypass, pass the first texture input through")
+            .clicked()
+        {
+            flags.bypass = !flags.bypass;
+        }
+        if ui
+            .selectable_label(flags.freeze, "F")
+            .on_hover_text("Freeze, keep the last rendered output")
+            .clicked()
+        {
+            flags.freeze = !flags.freeze;
+        }
+
+        if &flags != self.flags() {
+            vec![egui_node_graph::NodeResponse::User(
+                CustomGraphResponse::SetFlags(node_id, flags),
+            )]
+        } else {
+            vec![]
+        }
+    }
+
     fn bottom_ui(
         &self,
         ui: &mut egui::Ui,
@@ -91,40 +130,73 @@ impl NodeDataTrait for UiNodeData {
         ui.set_width(256.0);
         let node = &graph[node_id];
 
-        let tex_expanded = state.visible_nodes.contains(&node_id);
+        //value nodes have nothing to preview
+        let has_preview = !node
+            .outputs(graph)
+            .any(|output| output.typ != ConnectionType::Texture2D);
 
-        if tex_expanded {
-            if show_image(
-                ui,
-                node.user_data.texture.clone(),
-                ImageScale::MaxWidth(ui.available_width()),
-            )
-            .interact(egui::Sense::click())
-            .clicked()
-            {
-                state.visible_nodes.remove(&node_id);
-            };
-        } else {
-            if show_image(
-                ui,
-                node.user_data.texture.clone(),
-                ImageScale::MaxSize(50.0),
-            )
-            .interact(egui::Sense::click())
-            .clicked()
-            {
-                state.visible_nodes.insert(node_id);
-            }
-        }
+        if has_preview {
+            let tex_expanded = state.visible_nodes.contains(&node_id);
 
-        if ui.ui_contains_pointer() {
-            egui::show_tooltip_at_pointer(ui.ctx(), egui::Id::new("img_hover"), |ui| {
-                show_image(
+            if tex_expanded {
+                if show_image(
                     ui,
                     node.user_data.texture.clone(),
-                    ImageScale::MaxSize(200.0),
+                    ImageScale::MaxWidth(ui.available_width()),
                 )
-            });
+                .interact(egui::Sense::click())
+                .clicked()
+                {
+                    state.visible_nodes.remove(&node_id);
+                };
+            } else {
+                if show_image(
+                    ui,
+                    node.user_data.texture.clone(),
+                    ImageScale::MaxSize(50.0),
+                )
+                .interact(egui::Sense::click())
+                .clicked()
+                {
+                    state.visible_nodes.insert(node_id);
+                }
+            }
+
+            if ui.ui_contains_pointer() {
+                egui::show_tooltip_at_pointer(ui.ctx(), egui::Id::new("img_hover"), |ui| {
+                    show_image(
+                        ui,
+                        node.user_data.texture.clone(),
+                        ImageScale::MaxSize(200.0),
+                    )
+                });
+            }
+        }
+
+        let mut responses = vec![];
+
+        if let graph::NodeType::Subgraph { .. } = node.user_data.template() {
+            if ui.button("Enter subgraph").clicked() {
+                responses.push(egui_node_graph::NodeResponse::User(
+                    CustomGraphResponse::EnterSubgraph(node_id),
+                ));
+            }
+        }
+
+        if node.user_data.template().allocates_texture() {
+            let mut settings = *node.user_data.output_settings();
+
+            ui.collapsing("Output", |ui| draw_output_settings(ui, &mut settings));
+
+            if &settings != node.user_data.output_settings() {
+                responses.push(egui_node_graph::NodeResponse::User(
+                    CustomGraphResponse::SetOutputSettings(node_id, settings),
+                ));
+            }
+        }
+
+        if let Some(status) = state.processor.status(node_id) {
+            ui.label(status);
         }
 
         draw_error(ui, "Init", &node.user_data.create_error);
@@ -133,7 +205,41 @@ impl NodeDataTrait for UiNodeData {
 
         draw_time(ui, node.user_data.render_time);
 
-        vec![]
+        responses
+    }
+}
+
+fn draw_output_settings(ui: &mut egui::Ui, settings: &mut OutputSettings) {
+    ui.horizontal(|ui| {
+        ui.label("format");
+        for format in TextureFormat::ALL {
+            ui.selectable_value(&mut settings.format, format, format!("{format:?}"));
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("res");
+
+        let is_fraction = matches!(settings.res, OutputRes::Fraction(_));
+        if ui.selectable_label(is_fraction, "Fraction").clicked() && !is_fraction {
+            settings.res = OutputRes::Fraction(1.0);
+        }
+        if ui.selectable_label(!is_fraction, "Absolute").clicked() && is_fraction {
+            settings.res = OutputRes::Absolute(DEFAULT_RES.0, DEFAULT_RES.1);
+        }
+    });
+
+    match &mut settings.res {
+        OutputRes::Fraction(fraction) => {
+            ui.add(Slider::new(fraction, 0.125..=2.0).logarithmic(true));
+        }
+        OutputRes::Absolute(width, height) => {
+            ui.horizontal(|ui| {
+                ui.add(DragValue::new(width).clamp_range(1..=8192));
+                ui.label("x");
+                ui.add(DragValue::new(height).clamp_range(1..=8192));
+            });
+        }
     }
 }
 
@@ -93,6 +93,8 @@ pub fn popup<R>(
 fn draw_param(param: &mut UiValue, ui: &mut Ui, param_name: &str) -> ParamUiResponse {
     use graph::def::UiValue::*;
 
+    let pick_folder = matches!(param.0, Folder(_));
+
     match &mut param.0 {
         Vec2(data) => {
             ui.label(param_name);
@@ -175,7 +177,7 @@ fn draw_param(param: &mut UiValue, ui: &mut Ui, param_name: &str) -> ParamUiResp
             })
             .into(),
 
-        Path(path) => {
+        Path(path) | Folder(path) => {
             ui.horizontal(|ui| {
                 ui.label(param_name);
 
@@ -199,8 +201,14 @@ fn draw_param(param: &mut UiValue, ui: &mut Ui, param_name: &str) -> ParamUiResp
                 if ui.ui_contains_pointer() {
                     let files = &ui.ctx().input().raw.dropped_files;
                     if let Some(file) = files.iter().next() {
-                        if file.path.is_some() {
-                            *path = file.path.clone();
+                        if let Some(file_path) = &file.path {
+                            //dropping a file onto a folder param picks the folder it is in
+                            *path = match file_path.parent() {
+                                Some(parent) if pick_folder && file_path.is_file() => {
+                                    Some(parent.to_path_buf())
+                                }
+                                _ => Some(file_path.clone()),
+                            };
                         }
                     }
                 }
@@ -212,12 +220,16 @@ fn draw_param(param: &mut UiValue, ui: &mut Ui, param_name: &str) -> ParamUiResp
                         .flatten()
                         .unwrap_or(&"~");
 
-                    let new_path = native_dialog::FileDialog::new()
-                        .set_location(open_dir)
-                        .add_filter("OBJ file", &["obj"])
-                        // .add_filter("JPEG Image", &["jpg", "jpeg"])
-                        .show_open_single_file()
-                        .unwrap();
+                    let dialog = native_dialog::FileDialog::new().set_location(open_dir);
+                    let new_path = if pick_folder {
+                        dialog.show_open_single_dir()
+                    } else {
+                        dialog
+                            .add_filter("OBJ file", &["obj"])
+                            .add_filter("Image", &["png", "jpg", "jpeg", "tif", "tiff", "exr"])
+                            .show_open_single_file()
+                    }
+                    .unwrap();
 
                     if new_path.is_some() {
                         *path = new_path;
@@ -315,6 +327,7 @@ impl WidgetValueTrait for UiValue {
             .inner;
 
         let animator_popup_id = ui.make_persistent_id(param_key.clone());
+        let mut reset = false;
 
         if ui.rect_contains_pointer(param_response.response.rect)
             && ui.input().pointer.secondary_clicked()
@@ -327,6 +340,7 @@ impl WidgetValueTrait for UiValue {
                 ui.vertical(|ui| {
                     if ui.button("RESET").clicked() {
                         self.0.reset();
+                        reset = true;
                     }
 
                     ui.horizontal(|ui| {
@@ -353,7 +367,17 @@ impl WidgetValueTrait for UiValue {
                         if delete {
                             user_state.animator.animations.remove(&param_key);
                         }
-                    })
+                    });
+
+                    if let Some(exposed) = &mut user_state.exposed {
+                        let is_exposed = exposed.contains(&param_key);
+
+                        if is_exposed && ui.button("UNEXPOSE").clicked() {
+                            exposed.retain(|key| key != &param_key);
+                        } else if !is_exposed && ui.button("EXPOSE").clicked() {
+                            exposed.push(param_key.clone());
+                        }
+                    }
                 })
             });
 
@@ -382,6 +406,10 @@ impl WidgetValueTrait for UiValue {
         //     }
         // }
 
-        vec![]
+        if param_response.changed || reset {
+            vec![CustomGraphResponse::ValueChanged(node_id)]
+        } else {
+            vec![]
+        }
     }
 }
@@ -0,0 +1,55 @@
+use common::clock::{Clock, ClockMode};
+use egui::{DragValue, Ui};
+
+const DEFAULT_FPS: f32 = 60.0;
+
+///Play, pause, step and seek controls for the clock
+pub fn draw_transport(clock: &mut Clock, ui: &mut Ui) {
+    ui.horizontal(|ui| {
+        let play_label = if clock.is_playing() { "⏸" } else { "▶" };
+        if ui.button(play_label).on_hover_text("Play/pause").clicked() {
+            clock.toggle();
+        }
+
+        if ui.button("⏭").on_hover_text("Step one frame").clicked() {
+            clock.step();
+        }
+
+        if ui.button("⏮").on_hover_text("Back to the start").clicked() {
+            clock.reset();
+        }
+
+        let frame = clock.frame();
+        let mut time = frame.time;
+        let seek = ui.add(
+            DragValue::new(&mut time)
+                .speed(0.01)
+                .suffix("s")
+                .clamp_range(0.0..=f32::MAX),
+        );
+        if seek.changed() {
+            clock.seek(time);
+        }
+
+        ui.label(format!("frame {}", frame.frame_index));
+
+        ui.separator();
+
+        let mut fixed = matches!(clock.mode, ClockMode::Fixed { .. });
+        if ui
+            .checkbox(&mut fixed, "Fixed rate")
+            .on_hover_text("Advance the same time every frame, so renders are reproducible")
+            .changed()
+        {
+            clock.mode = if fixed {
+                ClockMode::Fixed { fps: DEFAULT_FPS }
+            } else {
+                ClockMode::RealTime
+            };
+        }
+
+        if let ClockMode::Fixed { fps } = &mut clock.mode {
+            ui.add(DragValue::new(fps).clamp_range(1.0..=240.0).suffix("fps"));
+        }
+    });
+}
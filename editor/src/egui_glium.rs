@@ -41,6 +41,7 @@ pub fn main() -> anyhow::Result<()> {
     egui_glium.egui_ctx.set_visuals(custom_visuals());
 
     let mut graph_ui = GraphUi::new_from_persistent(state, &display, &mut egui_glium)?;
+    graph_ui.resize(&display, display.get_framebuffer_dimensions());
 
     use signal_hook::consts::*;
 
@@ -71,6 +72,10 @@ pub fn main() -> anyhow::Result<()> {
             } => {
                 let egui_consumed_event = egui_glium.on_event(&window_ev);
 
+                if let WindowEvent::Resized(size) = window_ev {
+                    graph_ui.resize(&display, (size.width, size.height));
+                }
+
                 if !egui_consumed_event {
                     if matches!(
                         window_ev,
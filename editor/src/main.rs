@@ -1,12 +1,21 @@
 mod editor;
 mod egui_glium;
+mod offline_render;
 mod tree_view;
 mod widgets;
 
 fn main() -> color_eyre::Result<()> {
     color_eyre::install()?;
 
-    egui_glium::main().expect("Error running egui_glium::main()");
+    let mut args = std::env::args().skip(1);
+
+    if args.next().as_deref() == Some("render") {
+        let render_args =
+            offline_render::RenderArgs::parse(args).expect("Error parsing render arguments");
+        offline_render::render(render_args).expect("Error rendering project");
+    } else {
+        egui_glium::main().expect("Error running egui_glium::main()");
+    }
 
     Ok(())
 }
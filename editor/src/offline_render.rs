@@ -0,0 +1,209 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail};
+use audio::file::FileSource;
+use common::{
+    clock::{Clock, ClockMode},
+    headless::new_headless_facade,
+};
+use egui_node_graph::NodeId;
+use graph::{Animator, Graph, GraphState, NodeData, NodeType, TextureManager};
+use persistence::PersistentState;
+
+const USAGE: &str = "Usage: editor render [--project save.json] [--out dir] [--res 1920x1080] [--fps 30] [--duration 10] [--audio sound.wav] [--node label]";
+
+///Options for rendering a project to png frames
+pub struct RenderArgs {
+    pub project: PathBuf,
+    pub out_dir: PathBuf,
+    pub res: (u32, u32),
+    pub fps: f32,
+    ///Seconds
+    pub duration: f32,
+    ///Wav or flac file for the ISF audio inputs, played from the start
+    pub audio: Option<PathBuf>,
+    ///Label of the node to save, needed if the graph has more than one output
+    pub node: Option<String>,
+}
+
+impl Default for RenderArgs {
+    fn default() -> Self {
+        Self {
+            project: PersistentState::<NodeData>::default_path(),
+            out_dir: PathBuf::from("render"),
+            res: (1920, 1080),
+            fps: 30.0,
+            duration: 10.0,
+            audio: None,
+            node: None,
+        }
+    }
+}
+
+impl RenderArgs {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> anyhow::Result<Self> {
+        let mut render = Self::default();
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| anyhow!("Missing value for {arg}\n{USAGE}"))
+            };
+
+            match arg.as_str() {
+                "--project" => render.project = value()?.into(),
+                "--out" => render.out_dir = value()?.into(),
+                "--res" => render.res = parse_res(&value()?)?,
+                "--fps" => render.fps = value()?.parse()?,
+                "--duration" => render.duration = value()?.parse()?,
+                "--audio" => render.audio = Some(value()?.into()),
+                "--node" => render.node = Some(value()?),
+                other => bail!("Unknown argument {other}\n{USAGE}"),
+            }
+        }
+
+        if render.fps <= 0.0 {
+            bail!("fps must be positive");
+        }
+
+        Ok(render)
+    }
+}
+
+fn parse_res(text: &str) -> anyhow::Result<(u32, u32)> {
+    let (width, height) = text
+        .split_once('x')
+        .ok_or_else(|| anyhow!("Resolution should look like 1920x1080, not {text}"))?;
+
+    Ok((width.parse()?, height.parse()?))
+}
+
+///The node labelled label, or the only output of the graph
+fn find_output_node(
+    graph: &Graph,
+    graph_state: &GraphState,
+    label: Option<&str>,
+) -> anyhow::Result<NodeId> {
+    let candidates = match label {
+        Some(label) => graph
+            .iter_nodes()
+            .filter(|node_id| graph[*node_id].label == label)
+            .collect::<Vec<_>>(),
+        None => graph_state.processor.terminating_nodes().collect(),
+    };
+
+    match (candidates.as_slice(), label) {
+        ([node_id], _) => Ok(*node_id),
+        ([], Some(label)) => bail!("No node is labelled {label}"),
+        ([], None) => bail!("The graph has no output node"),
+        (_, Some(label)) => bail!("More than one node is labelled {label}"),
+        (_, None) => {
+            let mut labels = candidates
+                .iter()
+                .map(|node_id| graph[*node_id].label.as_str())
+                .collect::<Vec<_>>();
+            labels.sort_unstable();
+            bail!(
+                "The graph has several outputs, choose one with --node: {}",
+                labels.join(", ")
+            )
+        }
+    }
+}
+
+///Remove the nodes that publish or record their input, including inside subgraphs,
+/// so a headless render only writes its own frames
+fn remove_sink_nodes(graph: &mut Graph, animator: &mut Animator) {
+    let sinks = graph
+        .iter_nodes()
+        .filter(|node_id| {
+            matches!(
+                graph[*node_id].user_data.template,
+                NodeType::SharedOut | NodeType::Record
+            )
+        })
+        .collect::<Vec<_>>();
+
+    for node_id in sinks {
+        graph.remove_node(node_id);
+    }
+    animator
+        .animations
+        .retain(|(node_id, _), _| graph.nodes.contains_key(*node_id));
+
+    for node in graph.nodes.values_mut() {
+        if let NodeType::Subgraph { def, .. } = &mut node.user_data.template {
+            remove_sink_nodes(&mut def.graph, &mut def.animator);
+        }
+    }
+}
+
+///Render a saved project to a png for each frame, without opening the editor
+pub fn render(args: RenderArgs) -> anyhow::Result<()> {
+    let mut state: PersistentState = persistence::read_from_json_file(&args.project)?;
+
+    remove_sink_nodes(&mut state.graph, &mut state.animator);
+
+    let facade = new_headless_facade(args.res)?;
+    println!("GL Vendor: {}", facade.get_opengl_vendor_string());
+    println!("GL Version: {}", facade.get_opengl_version_string());
+
+    let mut graph_state = GraphState::from_persistent_state(
+        &mut state.graph,
+        state.node_names,
+        state.animator,
+        &facade,
+    )?;
+    let mut graph = state.graph;
+
+    let mut texture_manager = TextureManager::default();
+    if let Some(path) = &args.audio {
+        let source = FileSource::open(path)?;
+        texture_manager
+            .audio
+            .set_source(path.to_string_lossy(), Box::new(source));
+    }
+    graph_state
+        .processor
+        .resize(&graph, &facade, &mut texture_manager, args.res);
+
+    let node_id = find_output_node(&graph, &graph_state, args.node.as_deref())?;
+    let label = graph[node_id].label.clone();
+
+    std::fs::create_dir_all(&args.out_dir)?;
+
+    let mut clock = Clock::new(ClockMode::Fixed { fps: args.fps });
+    let frames = (args.duration * args.fps).ceil() as u32;
+
+    for frame in 0..frames {
+        let time = clock.tick();
+
+        for (node_id, err) in graph_state.update(&mut graph, &time, &facade) {
+            eprintln!("Update error in {}: {err:?}", graph[node_id].label);
+        }
+
+        let response = graph_state.processor.render_shaders(
+            &graph,
+            &facade,
+            &mut texture_manager,
+            &time,
+            |_, _| {},
+        );
+
+        for (node_id, err) in response.errors {
+            eprintln!("Render error in {}: {}", graph[node_id].label, err.text);
+        }
+
+        let image = graph_state
+            .processor
+            .read_output(node_id, None)
+            .ok_or_else(|| anyhow!("{label} has no output texture"))?;
+
+        let path = args.out_dir.join(format!("frame_{frame:05}.png"));
+        image.save(&path)?;
+
+        println!("Rendered {path:?}");
+    }
+
+    Ok(())
+}
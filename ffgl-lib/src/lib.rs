@@ -11,6 +11,7 @@ use glium::{
 };
 use itertools::Itertools;
 
+use common::clock::Clock;
 use graph::{def::UiValue, GraphState};
 use persistence::PersistentState;
 
@@ -67,6 +68,7 @@ pub struct Instance {
     ctx: Rc<Context>,
     params: Vec<node_param::NodeParam>,
     backend: Rc<gl_backend::RawGlBackend>,
+    clock: Clock,
 }
 
 impl std::fmt::Debug for Instance {
@@ -123,6 +125,7 @@ impl FFGLHandler for Instance {
             ctx,
             params: StaticState::get().params.clone(),
             backend,
+            clock: Clock::default(),
         }
     }
 
@@ -142,6 +145,15 @@ impl FFGLHandler for Instance {
         let res = inst_data.get_dimensions();
         self.ctx.rebuild(self.backend.clone()).unwrap();
 
+        if res != self.texture_manager.res {
+            self.graph_state.processor.resize(
+                &self.graph,
+                &self.ctx,
+                &mut self.texture_manager,
+                res,
+            );
+        }
+
         let frame = Frame::new(self.ctx.clone(), (res.0, res.1));
         let rb = RenderBuffer::new(
             &self.ctx,
@@ -176,7 +188,8 @@ impl FFGLHandler for Instance {
 }
 impl Instance {
     fn render_frame(&mut self, _inst_data: &ffgl::FFGLData, target: &mut impl Surface) {
-        self.graph_state.update(&mut self.graph, &self.ctx);
+        let time = self.clock.tick();
+        self.graph_state.update(&mut self.graph, &time, &self.ctx);
 
         for param in &self.params {
             // let node = self.graph.nodes.get(param.node_id).unwrap();
@@ -197,6 +210,7 @@ impl Instance {
             &mut self.graph,
             &self.ctx,
             &mut self.texture_manager,
+            &time,
             |_, _| {},
         );
 
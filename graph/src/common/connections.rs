@@ -1,17 +1,172 @@
-use crate::GraphState;
+use std::rc::Rc;
 
-use super::def::UiValue;
+use crate::{graph::graph_utils::SelectOutput, GraphState};
+
+use super::def::{AsUniformOptional, UiValue};
 use egui_node_graph::DataTypeTrait;
 use epaint::color::Hsva;
+use glam::Mat4;
+use glium::{
+    uniforms::{AsUniformValue, UniformValue},
+    Texture2d,
+};
 use serde::{Deserialize, Serialize};
 use strum::Display;
 
 #[derive(PartialEq, Eq, Display, Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum ConnectionType {
     Texture2D,
+    Float,
+    Vec2,
+    Vec4,
+    Color,
+    Mat4,
     None,
 }
 
+impl From<&UiValue> for ConnectionType {
+    fn from(value: &UiValue) -> Self {
+        match value {
+            UiValue::Float(_) => ConnectionType::Float,
+            UiValue::Vec2(_) => ConnectionType::Vec2,
+            UiValue::Vec4(_) => ConnectionType::Vec4,
+            UiValue::Color(_) => ConnectionType::Color,
+            UiValue::Mat4(_) => ConnectionType::Mat4,
+            _ => ConnectionType::None,
+        }
+    }
+}
+
+impl ConnectionType {
+    ///Value used for an unconnected input of this type
+    pub fn default_value(&self) -> UiValue {
+        match self {
+            ConnectionType::Float => UiValue::Float(0.0.into()),
+            ConnectionType::Vec2 => UiValue::Vec2([0.0; 2].into()),
+            ConnectionType::Vec4 => UiValue::Vec4([0.0; 4].into()),
+            ConnectionType::Color => UiValue::Color([1.0; 4].into()),
+            ConnectionType::Mat4 => UiValue::Mat4(Mat4::IDENTITY.into()),
+            ConnectionType::Texture2D | ConnectionType::None => UiValue::None,
+        }
+    }
+}
+
+///Data that is passed along a connection from an output to an input
+#[derive(Clone, Debug)]
+pub enum ConnectionValue {
+    Texture2D(Rc<Texture2d>),
+    Float(f32),
+    Vec2([f32; 2]),
+    Vec4([f32; 4]),
+    Color([f32; 4]),
+    Mat4(Mat4),
+}
+
+impl ConnectionValue {
+    pub fn ty(&self) -> ConnectionType {
+        match self {
+            ConnectionValue::Texture2D(_) => ConnectionType::Texture2D,
+            ConnectionValue::Float(_) => ConnectionType::Float,
+            ConnectionValue::Vec2(_) => ConnectionType::Vec2,
+            ConnectionValue::Vec4(_) => ConnectionType::Vec4,
+            ConnectionValue::Color(_) => ConnectionType::Color,
+            ConnectionValue::Mat4(_) => ConnectionType::Mat4,
+        }
+    }
+
+    pub fn texture(&self) -> Option<&Rc<Texture2d>> {
+        match self {
+            ConnectionValue::Texture2D(tex) => Some(tex),
+            _ => None,
+        }
+    }
+
+    ///The constant value of a parameter, if it can be sent along a connection
+    pub fn from_ui_value(value: &UiValue) -> Option<Self> {
+        match value {
+            UiValue::Float(v) => Some(ConnectionValue::Float(v.value)),
+            UiValue::Vec2(v) => Some(ConnectionValue::Vec2(v.value)),
+            UiValue::Vec4(v) => Some(ConnectionValue::Vec4(v.value)),
+            UiValue::Color(v) => Some(ConnectionValue::Color(v.value)),
+            UiValue::Mat4(v) => Some(ConnectionValue::Mat4(v.mat)),
+            _ => None,
+        }
+    }
+}
+
+///Textures are equal if they are the same texture
+impl PartialEq for ConnectionValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (ConnectionValue::Texture2D(a), ConnectionValue::Texture2D(b)) => Rc::ptr_eq(a, b),
+            (ConnectionValue::Float(a), ConnectionValue::Float(b)) => a == b,
+            (ConnectionValue::Vec2(a), ConnectionValue::Vec2(b)) => a == b,
+            (ConnectionValue::Vec4(a), ConnectionValue::Vec4(b)) => a == b,
+            (ConnectionValue::Color(a), ConnectionValue::Color(b)) => a == b,
+            (ConnectionValue::Mat4(a), ConnectionValue::Mat4(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+///Every output of a node.
+/// The main output is used for previews and for the first socket
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeOutputs {
+    pub main: ConnectionValue,
+    ///None for outputs that weren't produced this time
+    pub named: Vec<(String, Option<ConnectionValue>)>,
+}
+
+impl From<ConnectionValue> for NodeOutputs {
+    fn from(main: ConnectionValue) -> Self {
+        Self {
+            main,
+            named: vec![],
+        }
+    }
+}
+
+impl NodeOutputs {
+    ///The output called name, None if the node didn't produce it this time
+    pub fn get(&self, name: &str) -> Option<&ConnectionValue> {
+        self.named
+            .iter()
+            .find(|(output_name, _)| output_name == name)
+            .and_then(|(_, value)| value.as_ref())
+    }
+}
+
+impl SelectOutput for NodeOutputs {
+    type Output = ConnectionValue;
+
+    fn select(&self, name: Option<&str>) -> Option<ConnectionValue> {
+        match name {
+            Some(name) => self.get(name).cloned(),
+            None => Some(self.main.clone()),
+        }
+    }
+}
+
+impl From<Rc<Texture2d>> for ConnectionValue {
+    fn from(tex: Rc<Texture2d>) -> Self {
+        ConnectionValue::Texture2D(tex)
+    }
+}
+
+impl AsUniformOptional for ConnectionValue {
+    fn as_uniform_optional(&self) -> Option<UniformValue<'_>> {
+        match self {
+            ConnectionValue::Texture2D(tex) => Some(tex.as_ref().as_uniform_value()),
+            ConnectionValue::Float(v) => Some(v.as_uniform_value()),
+            ConnectionValue::Vec2(v) => Some(v.as_uniform_value()),
+            ConnectionValue::Vec4(v) => Some(v.as_uniform_value()),
+            ConnectionValue::Color(v) => Some(v.as_uniform_value()),
+            ConnectionValue::Mat4(v) => Some(UniformValue::Mat4(v.to_cols_array_2d())),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct InputDef {
     pub name: String,
@@ -42,15 +197,19 @@ impl InputDef {
 }
 
 impl<S: Into<String>, V: Into<UiValue>> From<(S, V)> for InputDef {
+    ///Creates an input that may be connected if the value type supports it
     fn from((name, val_ty): (S, V)) -> Self {
+        let value = val_ty.into();
+
         Self {
             name: name.into(),
-            ty: ConnectionType::None,
-            value: val_ty.into(),
+            ty: (&value).into(),
+            value,
         }
     }
 }
 
+#[derive(PartialEq)]
 pub struct OutputDef {
     pub name: String,
     pub ty: ConnectionType,
@@ -78,6 +237,11 @@ impl DataTypeTrait<GraphState> for ConnectionType {
     fn data_type_color(&self, _: &mut GraphState) -> epaint::Color32 {
         let hue = match self {
             ConnectionType::Texture2D => 0.7,
+            ConnectionType::Float => 0.1,
+            ConnectionType::Vec2 => 0.2,
+            ConnectionType::Vec4 => 0.3,
+            ConnectionType::Color => 0.9,
+            ConnectionType::Mat4 => 0.5,
             ConnectionType::None => 0.0,
         };
 
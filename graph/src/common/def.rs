@@ -20,6 +20,8 @@ pub enum UiValue {
     Color(RangedData<[f32; 4]>),
     Text(RangedData<String>, TextStyle),
     Path(Option<PathBuf>),
+    ///Path to a directory, picked with a folder dialog
+    Folder(Option<PathBuf>),
     Mat4(Mat4Animator),
 
     #[default]
@@ -62,7 +64,7 @@ impl Reset for UiValue {
                 v.reset();
                 *style = Default::default()
             }
-            UiValue::Path(optional_path) => *optional_path = None,
+            UiValue::Path(optional_path) | UiValue::Folder(optional_path) => *optional_path = None,
             UiValue::None => {}
         }
     }
@@ -84,7 +86,7 @@ impl AsUniformOptional for UiValue {
             UiValue::Menu(v, _) => Some(v.value.as_uniform_value()),
             UiValue::Mat4(v) => Some(UniformValue::Mat4(v.mat.to_cols_array_2d())),
 
-            UiValue::Text(..) | UiValue::Path(_) | UiValue::None => None,
+            UiValue::Text(..) | UiValue::Path(_) | UiValue::Folder(_) | UiValue::None => None,
         }
     }
 }
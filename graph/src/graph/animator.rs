@@ -1,5 +1,6 @@
-use std::{collections::HashMap, time::Instant};
+use std::{collections::HashMap, time::Duration};
 
+use common::clock::FrameTime;
 use egui_node_graph::NodeId;
 
 
@@ -12,13 +13,10 @@ use serde::{Deserialize, Serialize};
 
 use super::graph_change_listener::{GraphChangeEvent, GraphUpdateListener};
 
-#[derive(Default, Serialize, Deserialize, Clone)]
+#[derive(Default, Serialize, Deserialize, Clone, Debug)]
 pub struct Animator {
     #[serde(with = "vectorize")]
     pub animations: HashMap<(NodeId, String), DataUpdater>,
-
-    #[serde(skip)]
-    pub last_update: Option<Instant>,
 }
 
 impl<N, C, V> GraphUpdateListener<N, C, V> for Animator {
@@ -39,9 +37,13 @@ impl<N, C, V> GraphUpdateListener<N, C, V> for Animator {
 }
 
 impl Animator {
-    pub fn update<N, C, V: GetUiValue>(&mut self, graph: &mut egui_node_graph::Graph<N, C, V>) {
-        let elapsed_since_update = self.last_update.unwrap_or(Instant::now()).elapsed();
-        let update_info = UpdateInfo::new(elapsed_since_update);
+    ///Advance each animation by the time since the last frame
+    pub fn update<N, C, V: GetUiValue>(
+        &mut self,
+        graph: &mut egui_node_graph::Graph<N, C, V>,
+        time: &FrameTime,
+    ) {
+        let update_info = UpdateInfo::new(Duration::from_secs_f32(time.delta));
 
         for ((node_id, param_name), animation) in &self.animations {
             let maybe_input = graph.nodes[*node_id]
@@ -55,7 +57,5 @@ impl Animator {
                 animation.update_value(input_param.ui_value_mut(), &update_info);
             }
         }
-
-        self.last_update = Some(Instant::now());
     }
 }
@@ -0,0 +1,48 @@
+use egui_node_graph::NodeId;
+use slotmap::SecondaryMap;
+
+use super::graph_utils::ProcessedInputs;
+use crate::common::{
+    connections::{ConnectionValue, NodeOutputs},
+    def::UiValue,
+};
+use crate::def::GetUiValue;
+
+///What a node input looked like when the node was last cooked
+#[derive(PartialEq, Debug)]
+pub enum InputSnapshot {
+    ///Connected to a node, with the version of that node's output
+    Connected(NodeId, Option<u64>),
+    ///Set from outside of the graph
+    External(ConnectionValue),
+    Value(UiValue),
+}
+
+///Result of the last time a node was rendered or evaluated
+pub struct CookedNode {
+    pub inputs: Vec<InputSnapshot>,
+    pub output: NodeOutputs,
+    pub version: u64,
+}
+
+///Record the state of each input so it can be compared on the next frame
+pub fn snapshot_inputs<N, C, V: GetUiValue>(
+    graph: &egui_node_graph::Graph<N, C, V>,
+    inputs: &ProcessedInputs<'_, C, V, ConnectionValue>,
+    cooked: &SecondaryMap<NodeId, CookedNode>,
+) -> Vec<InputSnapshot> {
+    inputs
+        .iter()
+        .map(|(_, input, value)| match (graph.connection(input.id), value) {
+            (Some(output_id), _) => {
+                let upstream_id = graph[output_id].node;
+                InputSnapshot::Connected(
+                    upstream_id,
+                    cooked.get(upstream_id).map(|cooked| cooked.version),
+                )
+            }
+            (None, Some(value)) => InputSnapshot::External(value.clone()),
+            (None, None) => InputSnapshot::Value(input.value.ui_value().clone()),
+        })
+        .collect()
+}
@@ -2,9 +2,11 @@ use super::{animator::Animator, node_types::NodeType, GraphShaderProcessor, Grap
 use crate::{
     common::{connections::ConnectionType, def::UiValue},
     def::GetUiValue,
+    textures::{GetOutputSettings, OutputSettings},
     GetTemplate,
 };
 
+use common::clock::FrameTime;
 use egui_node_graph::{NodeId, UserResponseTrait};
 use glium::backend::Facade;
 use serde::{Deserialize, Serialize};
@@ -33,6 +35,26 @@ impl From<anyhow::Error> for NodeError {
 #[derive(Serialize, Deserialize, Clone)]
 pub struct NodeData {
     pub template: NodeType,
+
+    #[serde(default)]
+    pub output: OutputSettings,
+
+    #[serde(default)]
+    pub flags: NodeFlags,
+}
+
+///Show toggles that change how a node renders without rewiring it
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct NodeFlags {
+    ///Pass the first texture input straight through
+    pub bypass: bool,
+    ///Keep the last rendered output without rendering again
+    pub freeze: bool,
+}
+
+pub trait GetNodeFlags {
+    fn flags(&self) -> &NodeFlags;
+    fn flags_mut(&mut self) -> &mut NodeFlags;
 }
 
 impl GetTemplate for NodeData {
@@ -44,16 +66,42 @@ impl GetTemplate for NodeData {
     }
 }
 
+impl GetOutputSettings for NodeData {
+    fn output_settings(&self) -> &OutputSettings {
+        &self.output
+    }
+    fn output_settings_mut(&mut self) -> &mut OutputSettings {
+        &mut self.output
+    }
+}
+
+impl GetNodeFlags for NodeData {
+    fn flags(&self) -> &NodeFlags {
+        &self.flags
+    }
+    fn flags_mut(&mut self) -> &mut NodeFlags {
+        &mut self.flags
+    }
+}
+
 impl NodeData {
     pub fn new(template: NodeType) -> Self {
-        Self { template }
+        Self {
+            template,
+            output: OutputSettings::default(),
+            flags: NodeFlags::default(),
+        }
     }
 }
 
 impl Debug for NodeData {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut binding = f.debug_struct("UiNodeData");
-        binding.field("template", &self.template).finish()
+        binding
+            .field("template", &self.template)
+            .field("output", &self.output)
+            .field("flags", &self.flags)
+            .finish()
     }
 }
 
@@ -98,6 +146,10 @@ pub struct GraphState {
     pub processor: GraphShaderProcessor,
 
     pub animator: Animator,
+
+    ///Parameters shown on the containing node, if this graph is inside of a subgraph
+    #[serde(skip)]
+    pub exposed: Option<Vec<(NodeId, String)>>,
 }
 
 use crate::graph::graph_change_listener::MultipleUpdatesListener;
@@ -114,6 +166,7 @@ impl GraphState {
             animator,
             param_with_popup: None,
             visible_nodes: Default::default(),
+            exposed: None,
             processor: GraphShaderProcessor::new_from_graph(graph, facade)?,
         })
     }
@@ -136,10 +189,11 @@ impl GraphState {
     pub fn update<N: GetTemplate, C, V: GetUiValue>(
         &mut self,
         graph: &mut egui_node_graph::Graph<N, C, V>,
+        time: &FrameTime,
         facade: &impl glium::backend::Facade,
     ) -> SparseSecondaryMap<NodeId, anyhow::Error> {
-        let errors = self.processor.update(graph, facade);
-        self.animator.update(graph);
+        let errors = self.processor.update(graph, time, facade);
+        self.animator.update(graph, time);
         errors
     }
 }
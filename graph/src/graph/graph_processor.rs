@@ -4,32 +4,61 @@ use std::{
     time::{Duration, Instant},
 };
 
-use egui_node_graph::NodeId;
+use anyhow::anyhow;
+use common::clock::FrameTime;
+use egui_node_graph::{InputId, NodeId};
 use glium::{backend::Facade, Texture2d};
+use image::RgbaImage;
+use itertools::Itertools;
 
 use crate::{
     def::{AsUniformOptional, GetUiValue},
-    textures::TextureManager,
+    textures::{
+        readback::{read_texture, TextureReadback},
+        GetOutputSettings, TextureManager,
+    },
     GetTemplate,
 };
 use slotmap::{SecondaryMap, SparseSecondaryMap};
 
-use crate::common::connections::ConnectionType;
+use crate::common::connections::{ConnectionType, ConnectionValue, NodeOutputs};
 
 use super::{
+    cook::{snapshot_inputs, CookedNode, InputSnapshot},
     def::*,
     graph_change_listener::{GraphChangeEvent, GraphUpdateListener},
-    graph_utils::GraphMap,
+    graph_utils::{find_cycles, GraphMap, MapState, ProcessedInputs},
+    node_math::MathNode,
     node_shader::NodeShader,
     node_shader::ProcessedShaderNodeInputs,
+    node_types::NodeType,
     node_update::NodeUpdaters,
+    subgraph::SubgraphProcessor,
 };
 
 #[derive(Default)]
 pub struct GraphShaderProcessor {
     terminating_nodes: HashSet<NodeId>,
     shaders: SecondaryMap<NodeId, NodeShader>,
+    math_nodes: SecondaryMap<NodeId, MathNode>,
+    subgraphs: SecondaryMap<NodeId, SubgraphProcessor>,
     updater: NodeUpdaters,
+
+    ///Last output of each node, reused if nothing upstream changed
+    cooked: SecondaryMap<NodeId, CookedNode>,
+    ///Nodes that must be cooked next frame
+    dirty: HashSet<NodeId>,
+    ///Value that each feedback node received on the last frame
+    feedback: SecondaryMap<NodeId, Option<ConnectionValue>>,
+    ///Time of the last render, time dependent nodes only cook when it changes
+    last_time: Option<FrameTime>,
+
+    ///SubgraphOutput nodes, which pass on their input
+    port_outputs: HashSet<NodeId>,
+    ///Values given to SubgraphInput nodes by the outer graph
+    external_outputs: SecondaryMap<NodeId, ConnectionValue>,
+    ///Values given to exposed parameters by the outer graph
+    input_overrides: SecondaryMap<InputId, ConnectionValue>,
 }
 
 impl std::fmt::Debug for GraphShaderProcessor {
@@ -37,6 +66,12 @@ impl std::fmt::Debug for GraphShaderProcessor {
         f.debug_struct("ShaderGraphProcessor")
             .field("terminating_nodes", &self.terminating_nodes)
             .field("shaders", &self.shaders.len())
+            .field("math_nodes", &self.math_nodes.len())
+            .field("subgraphs", &self.subgraphs.len())
+            .field("cooked", &self.cooked.len())
+            .field("dirty", &self.dirty)
+            .field("feedback", &self.feedback.len())
+            .field("port_outputs", &self.port_outputs)
             .field("updater", &stringify!(NodeUpdaters))
             .finish()
     }
@@ -49,60 +84,359 @@ pub struct RenderResponse {
 }
 
 impl GraphShaderProcessor {
+    ///Tell the shader which of its outputs are used, rendering again if that changes what it draws
+    fn update_connected_outputs<N, C, V>(
+        &mut self,
+        graph: &egui_node_graph::Graph<N, C, V>,
+        node_id: NodeId,
+    ) {
+        if let Some(shader) = self.shaders.get_mut(node_id) {
+            let connected = graph[node_id]
+                .outputs
+                .iter()
+                .filter(|(_, output_id)| graph.connections.values().any(|id| id == output_id))
+                .map(|(name, _)| name.as_str())
+                .collect_vec();
+
+            if shader.set_connected_outputs(&connected) {
+                self.dirty.insert(node_id);
+            }
+        }
+    }
+
     fn add_dangling_output(&mut self, _facade: &impl Facade, node_id: NodeId) {
         self.terminating_nodes.insert(node_id);
     }
 
+    ///Nodes whose outputs aren't connected to anything, which are rendered each frame
+    pub fn terminating_nodes(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.terminating_nodes.iter().cloned()
+    }
+
+    ///The main output of a node from the last time it was cooked
+    pub fn output(&self, node_id: NodeId) -> Option<&ConnectionValue> {
+        self.cooked.get(node_id).map(|cooked| &cooked.output.main)
+    }
+
+    ///Every output of a node from the last time it was cooked
+    pub fn outputs(&self, node_id: NodeId) -> Option<&NodeOutputs> {
+        self.cooked.get(node_id).map(|cooked| &cooked.output)
+    }
+
+    ///Copy a node output into an image. Uses the main output if name is None.
+    /// Returns None if the node hasn't rendered that texture
+    pub fn read_output(&self, node_id: NodeId, name: Option<&str>) -> Option<RgbaImage> {
+        self.output_texture(node_id, name).map(read_texture)
+    }
+
+    ///Start copying a node output without waiting for the gpu, see [TextureReadback]
+    pub fn start_readback(&self, node_id: NodeId, name: Option<&str>) -> Option<TextureReadback> {
+        self.output_texture(node_id, name).map(TextureReadback::start)
+    }
+
+    fn output_texture(&self, node_id: NodeId, name: Option<&str>) -> Option<&Texture2d> {
+        let outputs = self.outputs(node_id)?;
+        let value = match name {
+            Some(name) => outputs.get(name)?,
+            None => &outputs.main,
+        };
+        value.texture().map(|tex| tex.as_ref())
+    }
+
+    ///What a node is doing, such as where it is recording to
+    pub fn status(&self, node_id: NodeId) -> Option<String> {
+        self.shaders.get(node_id).and_then(NodeShader::status)
+    }
+
+    ///If any node changes output every frame
+    pub fn is_time_dependent(&self) -> bool {
+        !self.feedback.is_empty()
+            || self.shaders.values().any(NodeShader::is_time_dependent)
+            || self.subgraphs.values().any(SubgraphProcessor::is_time_dependent)
+    }
+
+    ///If any node will be cooked next frame, even if its inputs stay the same
+    pub fn is_dirty(&self) -> bool {
+        !self.dirty.is_empty()
+    }
+
+    ///Change the project resolution.
+    /// Reallocates textures and pass buffers, then recooks every node
+    pub fn resize<N: GetTemplate + GetOutputSettings, C, V>(
+        &mut self,
+        graph: &egui_node_graph::Graph<N, C, V>,
+        facade: &impl Facade,
+        texture_manager: &mut TextureManager,
+        res: (u32, u32),
+    ) {
+        texture_manager.set_res(res);
+
+        for (node_id, shader) in self.shaders.iter_mut() {
+            let node_res = graph[node_id].user_data.output_settings().res.resolve(res);
+            shader.resize(node_res);
+        }
+
+        for (node_id, subgraph) in self.subgraphs.iter_mut() {
+            if let NodeType::Subgraph { def, .. } = graph[node_id].user_data.template() {
+                subgraph.resize(def, facade, texture_manager, res);
+            }
+        }
+
+        //cooked textures are the old size
+        self.cooked.clear();
+        for value in self.feedback.values_mut() {
+            *value = None;
+        }
+    }
+
+    ///Cook a node on the next render, such as after its settings change
+    pub fn mark_dirty(&mut self, node_id: NodeId) {
+        self.dirty.insert(node_id);
+    }
+
+    ///Set the values that come from outside of the graph when it is used as a subgraph.
+    /// Nodes that receive them are cooked on the next render
+    pub fn set_external(
+        &mut self,
+        external_outputs: SecondaryMap<NodeId, ConnectionValue>,
+        input_overrides: SecondaryMap<InputId, ConnectionValue>,
+    ) {
+        self.dirty.extend(external_outputs.keys());
+        self.external_outputs = external_outputs;
+        self.input_overrides = input_overrides;
+    }
+
     ///Processes each shader in the output_targets list from start to end
     /// Generates ui textures
     /// processes inputs
+    /// Only cooks nodes whose inputs, source or time have changed
+    /// Bypassed nodes pass on their first texture input, frozen nodes keep their last output
     /// Returns a list of output textures
-    pub fn render_shaders<'a, N, C, V: AsUniformOptional>(
+    pub fn render_shaders<
+        'a,
+        N: GetTemplate + GetOutputSettings + GetNodeFlags,
+        C,
+        V: AsUniformOptional + GetUiValue,
+    >(
         &mut self,
-        graph: &mut egui_node_graph::Graph<N, C, V>,
+        graph: &egui_node_graph::Graph<N, C, V>,
         facade: &impl Facade,
         texture_manager: &mut TextureManager,
+        time: &FrameTime,
         mut node_post_render: impl FnMut(NodeId, &Texture2d),
     ) -> RenderResponse {
         let mut errors: SparseSecondaryMap<NodeId, NodeError> = Default::default();
         let mut times: SecondaryMap<NodeId, Duration> = Default::default();
 
+        //a paused clock repeats the same frame
+        let time_changed = self.last_time != Some(*time);
+        self.last_time = Some(*time);
+
+        //feedback nodes output last frame, so their inputs are computed at the end
+        let feedback_ids: Vec<NodeId> = self.feedback.keys().collect();
+        let mut state = MapState::new(feedback_ids.iter().cloned().collect());
+
+        let cycles = find_cycles(graph, &state.skip_inputs);
+
+        let mut on_node = |node_id: NodeId,
+                           inputs: ProcessedInputs<'_, C, V, ConnectionValue>|
+         -> Option<NodeOutputs> {
+            let mut inputs = inputs;
+            for (_, input, value) in inputs.iter_mut() {
+                if let Some(external) = self.input_overrides.get(input.id) {
+                    *value = Some(external.clone());
+                }
+            }
+
+            let flags = graph[node_id].user_data.flags();
+            if flags.freeze && !flags.bypass {
+                if let Some(cooked) = self.cooked.get(node_id) {
+                    return Some(cooked.output.clone());
+                }
+            }
+
+            let mut snapshot = snapshot_inputs(graph, &inputs, &self.cooked);
+            if let Some(external) = self.external_outputs.get(node_id) {
+                snapshot.push(InputSnapshot::External(external.clone()));
+            }
+
+            if flags.bypass {
+                //a new version is only needed when the texture being passed on changes
+                if !self.dirty.remove(&node_id) {
+                    if let Some(cooked) = self.cooked.get(node_id) {
+                        if cooked.inputs == snapshot {
+                            return Some(cooked.output.clone());
+                        }
+                    }
+                }
+
+                let passthrough = inputs.iter().find_map(|(_, _, value)| {
+                    value
+                        .clone()
+                        .filter(|value| value.ty() == ConnectionType::Texture2D)
+                });
+
+                let passthrough = match passthrough {
+                    Some(passthrough) => passthrough,
+                    None => {
+                        self.cooked.remove(node_id);
+                        return None;
+                    }
+                };
+
+                if let Some(target) = passthrough.texture() {
+                    node_post_render(node_id, target.as_ref());
+                }
+
+                let output = NodeOutputs::from(passthrough);
+                let version = self
+                    .cooked
+                    .get(node_id)
+                    .map(|cooked| cooked.version + 1)
+                    .unwrap_or_default();
+
+                self.cooked.insert(
+                    node_id,
+                    CookedNode {
+                        inputs: snapshot,
+                        output: output.clone(),
+                        version,
+                    },
+                );
+
+                return Some(output);
+            }
+
+            let forced = self.dirty.remove(&node_id);
+            let time_dependent = time_changed
+                && (self.feedback.contains_key(node_id)
+                    || self
+                        .shaders
+                        .get(node_id)
+                        .map(NodeShader::is_time_dependent)
+                        .unwrap_or(false)
+                    || self
+                        .subgraphs
+                        .get(node_id)
+                        .map(SubgraphProcessor::is_time_dependent)
+                        .unwrap_or(false));
+
+            //Nothing changed since the last cook
+            if !forced && !time_dependent {
+                if let Some(cooked) = self.cooked.get(node_id) {
+                    if cooked.inputs == snapshot {
+                        return Some(cooked.output.clone());
+                    }
+                }
+            }
+
+            //Render a shader
+            let start = Instant::now();
+            let result = if let Some(shader) = self.shaders.get_mut(node_id) {
+                shader
+                    .render(
+                        facade,
+                        texture_manager,
+                        graph[node_id].user_data.output_settings(),
+                        time,
+                        ProcessedShaderNodeInputs::from(&inputs),
+                    )
+                    .map(Some)
+            } else if let Some(math) = self.math_nodes.get(node_id) {
+                //Compute a value on the cpu
+                math.evaluate(&inputs).map(|value| Some(value.into()))
+            } else if let Some(previous) = self.feedback.get(node_id) {
+                Ok(previous.clone().map(NodeOutputs::from))
+            } else if let Some(subgraph) = self.subgraphs.get_mut(node_id) {
+                //Render the inner graph
+                match graph[node_id].user_data.template() {
+                    NodeType::Subgraph { def, .. } => {
+                        subgraph.render(def, facade, texture_manager, time, &inputs)
+                    }
+                    _ => Ok(None),
+                }
+            } else if let Some(external) = self.external_outputs.get(node_id) {
+                Ok(Some(external.clone().into()))
+            } else if self.port_outputs.contains(&node_id) {
+                Ok(inputs
+                    .iter()
+                    .find_map(|(_, _, value)| value.clone())
+                    .map(NodeOutputs::from))
+            } else {
+                Ok(None)
+            };
+
+            match result {
+                Ok(Some(output)) => {
+                    times.insert(node_id, start.elapsed());
+
+                    if let Some(target) = output.main.texture() {
+                        node_post_render(node_id, target.as_ref());
+                    }
+
+                    let version = self
+                        .cooked
+                        .get(node_id)
+                        .map(|cooked| cooked.version + 1)
+                        .unwrap_or_default();
+
+                    self.cooked.insert(
+                        node_id,
+                        CookedNode {
+                            inputs: snapshot,
+                            output: output.clone(),
+                            version,
+                        },
+                    );
+
+                    Some(output)
+                }
+                Ok(None) => None,
+                Err(err) => {
+                    //retry next frame
+                    self.cooked.remove(node_id);
+                    errors.insert(node_id, err.into());
+                    None
+                }
+            }
+        };
+
         let outputs = self
             .terminating_nodes
             .iter()
             .cloned()
             .map(|output_id| {
-                graph.map_with_inputs(
-                    output_id,
-                    &mut |node_id, inputs| {
-                        //Render a shader
-                        let start = Instant::now();
-                        if let Some(shader) = self.shaders.get_mut(node_id) {
-                            match shader.render(
-                                facade,
-                                texture_manager,
-                                ProcessedShaderNodeInputs::from(&inputs),
-                            ) {
-                                Ok(target) => {
-                                    node_post_render(node_id, &target);
-                                    times.insert(node_id, start.elapsed());
-                                    Some(target)
-                                }
-
-                                Err(err) => {
-                                    errors.insert(node_id, err.into());
-                                    None
-                                }
-                            }
-                        } else {
-                            None
-                        }
-                    },
-                    &mut SecondaryMap::new(),
-                )
+                graph
+                    .map_with_inputs(output_id, &mut on_node, &mut state)
+                    .and_then(|outputs| outputs.main.texture().cloned())
             })
             .collect();
 
+        //store the input of each feedback node for the next frame
+        let next_feedback = feedback_ids
+            .into_iter()
+            .map(|feedback_id| {
+                let value = graph
+                    .compute_inputs(feedback_id, &mut on_node, &mut state)
+                    .into_iter()
+                    .find_map(|(_, _, value)| value);
+
+                (feedback_id, value)
+            })
+            .collect_vec();
+
+        for (feedback_id, value) in next_feedback {
+            self.feedback.insert(feedback_id, value);
+        }
+
+        for node_id in cycles {
+            errors.insert(
+                node_id,
+                anyhow!("Cycle in graph, connect through a Feedback node to use the last frame")
+                    .into(),
+            );
+        }
+
         RenderResponse {
             terminating_textures: outputs,
             errors,
@@ -121,42 +455,68 @@ impl<N: GetTemplate, V> GraphUpdateListener<N, ConnectionType, V> for GraphShade
     ) -> anyhow::Result<()> {
         match event {
             GraphChangeEvent::CreatedNode(node_id) => {
-                let template = graph[node_id].user_data.template();
+                let template = graph.nodes[node_id].user_data.template_mut();
 
                 //only add if needed ()s
                 if let Some(shader) = NodeShader::new(template, facade) {
                     self.shaders.insert(node_id, shader?);
                 }
 
+                match template {
+                    NodeType::Math { op, ty } => {
+                        self.math_nodes.insert(node_id, MathNode::new(*op, *ty));
+                    }
+                    NodeType::Feedback => {
+                        self.feedback.insert(node_id, None);
+                    }
+                    NodeType::Subgraph { def, .. } => {
+                        self.subgraphs
+                            .insert(node_id, SubgraphProcessor::new(def, facade)?);
+                    }
+                    NodeType::SubgraphOutput { .. } => {
+                        self.port_outputs.insert(node_id);
+                    }
+                    _ => {}
+                }
+
                 //remove output target if not needed
                 for input in graph[node_id].inputs(graph) {
-                    if input.typ == ConnectionType::Texture2D {
-                        let connected_output = graph.connection(input.id);
-                        if let Some(output_id) = connected_output {
-                            let connected_node_id = graph[output_id].node;
+                    let connected_output = graph.connection(input.id);
+                    if let Some(output_id) = connected_output {
+                        let connected_node_id = graph[output_id].node;
 
-                            self.terminating_nodes.remove(&connected_node_id);
-                        }
+                        self.terminating_nodes.remove(&connected_node_id);
                     }
                 }
 
                 self.add_dangling_output(facade, node_id);
+                self.update_connected_outputs(graph, node_id);
             }
 
             //may create new output target
             GraphChangeEvent::Disconnected { output_id, .. } => {
                 if let Some(output) = graph.try_get_output(output_id) {
                     self.add_dangling_output(facade, output.node);
+                    self.update_connected_outputs(graph, output.node);
                 }
             }
 
             GraphChangeEvent::Connected { output_id, .. } => {
-                self.terminating_nodes.remove(&graph[output_id].node);
+                let node_id = graph[output_id].node;
+                self.terminating_nodes.remove(&node_id);
+                self.update_connected_outputs(graph, node_id);
             }
 
             GraphChangeEvent::DestroyedNode(node_id) => {
                 self.terminating_nodes.remove(&node_id);
                 self.shaders.remove(node_id);
+                self.math_nodes.remove(node_id);
+                self.subgraphs.remove(node_id);
+                self.port_outputs.remove(&node_id);
+                self.external_outputs.remove(node_id);
+                self.cooked.remove(node_id);
+                self.dirty.remove(&node_id);
+                self.feedback.remove(node_id);
             }
         }
 
@@ -170,8 +530,33 @@ impl GraphShaderProcessor {
     pub fn update<N: GetTemplate, C, V: GetUiValue>(
         &mut self,
         graph: &mut egui_node_graph::Graph<N, C, V>,
+        time: &FrameTime,
         facade: &impl Facade,
     ) -> SparseSecondaryMap<NodeId, anyhow::Error> {
-        self.updater.update(&mut self.shaders, graph, facade)
+        let mut errors = self
+            .updater
+            .update(&mut self.shaders, graph, facade, &mut self.dirty);
+
+        for (node_id, subgraph) in self.subgraphs.iter_mut() {
+            let values: Vec<_> = graph[node_id]
+                .inputs
+                .iter()
+                .map(|(name, input_id)| (name.clone(), graph[*input_id].value.ui_value().clone()))
+                .collect();
+
+            if let NodeType::Subgraph { def, .. } = graph.nodes[node_id].user_data.template_mut() {
+                match subgraph.update(def, &values, time, facade) {
+                    Ok(true) => {
+                        self.dirty.insert(node_id);
+                    }
+                    Ok(false) => {}
+                    Err(err) => {
+                        errors.insert(node_id, err);
+                    }
+                }
+            }
+        }
+
+        errors
     }
 }
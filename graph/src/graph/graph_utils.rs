@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use egui_node_graph::{InputParam, NodeId};
 
 use slotmap::SecondaryMap;
@@ -5,29 +7,63 @@ use slotmap::SecondaryMap;
 pub type InputParams<'a, C, V> = Vec<(&'a str, &'a InputParam<C, V>)>;
 pub type ProcessedInputs<'a, C, V, OUT> = Vec<(&'a str, &'a InputParam<C, V>, Option<OUT>)>;
 
+///Result of a node, which may have several named outputs
+pub trait SelectOutput: Clone {
+    type Output: Clone;
+
+    ///The value of the output called name, or of the first output if None
+    fn select(&self, name: Option<&str>) -> Option<Self::Output>;
+}
+
+///State kept while mapping over a graph
+pub struct MapState<OUT> {
+    pub cache: SecondaryMap<NodeId, Option<OUT>>,
+    ///Nodes whose inputs are not followed (used to break cycles)
+    pub skip_inputs: HashSet<NodeId>,
+    ///Nodes that are currently computing their inputs
+    stack: Vec<NodeId>,
+}
+
+impl<OUT> MapState<OUT> {
+    pub fn new(skip_inputs: HashSet<NodeId>) -> Self {
+        Self {
+            cache: SecondaryMap::new(),
+            skip_inputs,
+            stack: Vec::new(),
+        }
+    }
+}
+
+impl<OUT> Default for MapState<OUT> {
+    fn default() -> Self {
+        Self::new(HashSet::new())
+    }
+}
+
 ///Trait for internal mapping over a graph
-pub trait GraphMap<C, V, FOnNode, OUT: Clone>
+pub trait GraphMap<C, V, FOnNode, OUT: SelectOutput>
 where
-    FOnNode: FnMut(NodeId, ProcessedInputs<'_, C, V, OUT>) -> Option<OUT>,
+    FOnNode: FnMut(NodeId, ProcessedInputs<'_, C, V, OUT::Output>) -> Option<OUT>,
 {
     fn map_with_inputs(
         &self,
         node_id: NodeId,
         f_on_node: &mut FOnNode,
-        cache: &mut SecondaryMap<NodeId, Option<OUT>>,
+        state: &mut MapState<OUT>,
     ) -> Option<OUT>;
 
     fn compute_inputs(
         &self,
         node_id: NodeId,
         f_on_node: &mut FOnNode,
-        cache: &mut SecondaryMap<NodeId, Option<OUT>>,
-    ) -> ProcessedInputs<C, V, OUT>;
+        state: &mut MapState<OUT>,
+    ) -> ProcessedInputs<C, V, OUT::Output>;
 }
 
-impl<N, C, V, FOnNode, OUT: Clone> GraphMap<C, V, FOnNode, OUT> for egui_node_graph::Graph<N, C, V>
+impl<N, C, V, FOnNode, OUT: SelectOutput> GraphMap<C, V, FOnNode, OUT>
+    for egui_node_graph::Graph<N, C, V>
 where
-    FOnNode: FnMut(NodeId, ProcessedInputs<'_, C, V, OUT>) -> Option<OUT>,
+    FOnNode: FnMut(NodeId, ProcessedInputs<'_, C, V, OUT::Output>) -> Option<OUT>,
 {
     ///Call f for each node in correct order, ending on node_id\
     ///
@@ -37,9 +73,21 @@ where
         &self,
         node_id: NodeId,
         f_on_node: &mut FOnNode,
-        cache: &mut SecondaryMap<NodeId, Option<OUT>>,
+        state: &mut MapState<OUT>,
     ) -> Option<OUT> {
-        let computed_inputs = self.compute_inputs(node_id, f_on_node, cache);
+        let computed_inputs = if state.skip_inputs.contains(&node_id) {
+            self[node_id]
+                .inputs
+                .iter()
+                .map(|(name, input_id)| (name.as_str(), &self[*input_id], None))
+                .collect()
+        } else {
+            state.stack.push(node_id);
+            let computed_inputs = self.compute_inputs(node_id, f_on_node, state);
+            state.stack.pop();
+            computed_inputs
+        };
+
         let result = f_on_node(node_id, computed_inputs);
         result
     }
@@ -48,8 +96,8 @@ where
         &self,
         node_id: NodeId,
         f_on_node: &mut FOnNode,
-        cache: &mut SecondaryMap<NodeId, Option<OUT>>,
-    ) -> ProcessedInputs<C, V, OUT> {
+        state: &mut MapState<OUT>,
+    ) -> ProcessedInputs<C, V, OUT::Output> {
         self[node_id]
             .inputs
             .iter()
@@ -62,13 +110,30 @@ where
                         //we get to process a node!
                         let processing_node_id = self[output_id].node;
 
+                        //still computing this node, so this is a cycle
+                        if state.stack.contains(&processing_node_id) {
+                            return None;
+                        }
+
                         //add input to cache if doesn't exist
-                        if !cache.contains_key(processing_node_id) {
-                            let value = self.map_with_inputs(processing_node_id, f_on_node, cache);
-                            cache.insert(processing_node_id, value);
+                        if !state.cache.contains_key(processing_node_id) {
+                            let value = self.map_with_inputs(processing_node_id, f_on_node, state);
+                            state.cache.insert(processing_node_id, value);
                         }
 
-                        cache[processing_node_id].clone()
+                        //the first output is the main one, whatever it is called
+                        let output_name = match self[processing_node_id]
+                            .outputs
+                            .iter()
+                            .position(|(_, id)| *id == output_id)?
+                        {
+                            0 => None,
+                            index => Some(self[processing_node_id].outputs[index].0.as_str()),
+                        };
+
+                        state.cache[processing_node_id]
+                            .as_ref()
+                            .and_then(|out| out.select(output_name))
                     })
                     .flatten();
 
@@ -77,3 +142,46 @@ where
             .collect()
     }
 }
+
+///Find every node that is part of a cycle, ignoring the inputs of skip_inputs
+pub fn find_cycles<N, C, V>(
+    graph: &egui_node_graph::Graph<N, C, V>,
+    skip_inputs: &HashSet<NodeId>,
+) -> HashSet<NodeId> {
+    fn visit<N, C, V>(
+        graph: &egui_node_graph::Graph<N, C, V>,
+        node_id: NodeId,
+        skip_inputs: &HashSet<NodeId>,
+        stack: &mut Vec<NodeId>,
+        done: &mut HashSet<NodeId>,
+        cycles: &mut HashSet<NodeId>,
+    ) {
+        if let Some(pos) = stack.iter().position(|id| *id == node_id) {
+            cycles.extend(stack[pos..].iter().cloned());
+            return;
+        }
+
+        if done.contains(&node_id) || skip_inputs.contains(&node_id) {
+            return;
+        }
+
+        stack.push(node_id);
+        for (_, input_id) in &graph[node_id].inputs {
+            if let Some(output_id) = graph.connection(*input_id) {
+                visit(graph, graph[output_id].node, skip_inputs, stack, done, cycles);
+            }
+        }
+        stack.pop();
+
+        done.insert(node_id);
+    }
+
+    let mut done = HashSet::new();
+    let mut cycles = HashSet::new();
+
+    for node_id in graph.nodes.keys() {
+        visit(graph, node_id, skip_inputs, &mut vec![], &mut done, &mut cycles);
+    }
+
+    cycles
+}
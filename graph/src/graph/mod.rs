@@ -1,9 +1,13 @@
+mod cook;
 pub mod graph_change_listener;
 mod graph_processor;
 pub mod graph_utils;
+pub mod node_math;
 pub mod node_shader;
 mod node_update;
+pub mod record_out_shader;
 mod spout_out_shader;
+pub mod subgraph;
 pub use graph_processor::GraphShaderProcessor;
 pub mod animator;
 pub mod def;
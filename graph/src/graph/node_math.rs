@@ -0,0 +1,370 @@
+use anyhow::anyhow;
+use glam::Vec4;
+use serde::{Deserialize, Serialize};
+use strum::{EnumIter, IntoEnumIterator, IntoStaticStr};
+
+use super::graph_utils::ProcessedInputs;
+use crate::common::connections::{ConnectionType, ConnectionValue, InputDef};
+use crate::common::def::{RangedData, UiValue};
+use crate::def::GetUiValue;
+
+///Operations that are computed on the cpu instead of in a shader
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize, EnumIter, IntoStaticStr)]
+pub enum MathOp {
+    Add,
+    Multiply,
+    Mix,
+    Clamp,
+    Remap,
+    Length,
+    Normalize,
+    Sin,
+    Cos,
+    Compose,
+    Decompose,
+}
+
+const COMPONENTS: [&str; 4] = ["x", "y", "z", "w"];
+
+fn component_count(ty: ConnectionType) -> usize {
+    match ty {
+        ConnectionType::Float => 1,
+        ConnectionType::Vec2 => 2,
+        ConnectionType::Vec4 | ConnectionType::Color => 4,
+        _ => 0,
+    }
+}
+
+impl MathOp {
+    pub fn name(&self) -> &'static str {
+        self.into()
+    }
+
+    ///Types this op can be created with
+    pub fn valid_types(&self) -> &'static [ConnectionType] {
+        use ConnectionType::*;
+
+        match self {
+            MathOp::Multiply => &[Float, Vec2, Vec4, Color, Mat4],
+            MathOp::Add | MathOp::Mix | MathOp::Clamp | MathOp::Remap => {
+                &[Float, Vec2, Vec4, Color]
+            }
+            MathOp::Sin | MathOp::Cos => &[Float, Vec2, Vec4],
+            MathOp::Length | MathOp::Normalize => &[Vec2, Vec4],
+            MathOp::Compose | MathOp::Decompose => &[Vec2, Vec4, Color],
+        }
+    }
+
+    ///Every valid combination of op and type
+    pub fn all() -> impl Iterator<Item = (MathOp, ConnectionType)> {
+        MathOp::iter().flat_map(|op| op.valid_types().iter().map(move |ty| (op, *ty)))
+    }
+
+    pub fn input_types(&self, ty: ConnectionType) -> Vec<InputDef> {
+        let value = |name: &str| InputDef::from((name, ty.default_value()));
+        let float = |name: &str, val: f32| InputDef::from((name, UiValue::Float(val.into())));
+
+        match self {
+            MathOp::Add | MathOp::Multiply => vec![value("a"), value("b")],
+            MathOp::Mix => vec![value("a"), value("b"), float("t", 0.5)],
+            MathOp::Clamp => vec![value("value"), value("min"), value("max")],
+            MathOp::Remap => vec![
+                value("value"),
+                value("in_min"),
+                value("in_max"),
+                value("out_min"),
+                value("out_max"),
+            ],
+            MathOp::Length | MathOp::Normalize | MathOp::Sin | MathOp::Cos => {
+                vec![value("value")]
+            }
+            MathOp::Compose => COMPONENTS[..component_count(ty)]
+                .iter()
+                .map(|name| float(name, 0.0))
+                .collect(),
+            MathOp::Decompose => {
+                let mapping = COMPONENTS[..component_count(ty)]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, name)| (name.to_string(), i as i32))
+                    .collect();
+
+                vec![
+                    value("value"),
+                    ("component", UiValue::Menu(RangedData::from(0), mapping)).into(),
+                ]
+            }
+        }
+    }
+
+    pub fn output_type(&self, ty: ConnectionType) -> ConnectionType {
+        match self {
+            MathOp::Length | MathOp::Decompose => ConnectionType::Float,
+            _ => ty,
+        }
+    }
+}
+
+///A math node that is evaluated each frame
+#[derive(Clone, Copy, Debug)]
+pub struct MathNode {
+    pub op: MathOp,
+    pub ty: ConnectionType,
+}
+
+fn to_vec4(value: &ConnectionValue) -> Option<Vec4> {
+    match value {
+        ConnectionValue::Float(v) => Some(Vec4::new(*v, 0.0, 0.0, 0.0)),
+        ConnectionValue::Vec2(v) => Some(Vec4::new(v[0], v[1], 0.0, 0.0)),
+        ConnectionValue::Vec4(v) | ConnectionValue::Color(v) => Some(Vec4::from_array(*v)),
+        ConnectionValue::Texture2D(_) | ConnectionValue::Mat4(_) => None,
+    }
+}
+
+fn from_vec4(ty: ConnectionType, v: Vec4) -> Option<ConnectionValue> {
+    match ty {
+        ConnectionType::Float => Some(ConnectionValue::Float(v.x)),
+        ConnectionType::Vec2 => Some(ConnectionValue::Vec2([v.x, v.y])),
+        ConnectionType::Vec4 => Some(ConnectionValue::Vec4(v.to_array())),
+        ConnectionType::Color => Some(ConnectionValue::Color(v.to_array())),
+        _ => None,
+    }
+}
+
+fn input_value<C, V: GetUiValue>(
+    inputs: &ProcessedInputs<'_, C, V, ConnectionValue>,
+    name: &str,
+) -> Option<ConnectionValue> {
+    inputs
+        .iter()
+        .find(|(input_name, ..)| *input_name == name)
+        .and_then(|(_, input, connected)| {
+            connected
+                .clone()
+                .or_else(|| ConnectionValue::from_ui_value(input.value.ui_value()))
+        })
+}
+
+impl MathNode {
+    pub fn new(op: MathOp, ty: ConnectionType) -> Self {
+        Self { op, ty }
+    }
+
+    pub fn evaluate<C, V: GetUiValue>(
+        &self,
+        inputs: &ProcessedInputs<'_, C, V, ConnectionValue>,
+    ) -> anyhow::Result<ConnectionValue> {
+        let component = inputs
+            .iter()
+            .find(|(name, ..)| *name == "component")
+            .and_then(|(_, input, _)| match input.value.ui_value() {
+                UiValue::Menu(data, _) => Some(data.value as usize),
+                _ => None,
+            });
+
+        self.compute(|name| input_value(inputs, name), component)
+    }
+
+    ///Apply the op to the inputs, looked up by name. component is the menu of Decompose
+    fn compute(
+        &self,
+        input_value: impl Fn(&str) -> Option<ConnectionValue>,
+        component: Option<usize>,
+    ) -> anyhow::Result<ConnectionValue> {
+        let missing = |name: &str| anyhow!("Missing input {name} for {}", self.op.name());
+
+        if self.ty == ConnectionType::Mat4 {
+            let mat = |name: &str| match input_value(name) {
+                Some(ConnectionValue::Mat4(mat)) => Ok(mat),
+                _ => Err(missing(name)),
+            };
+
+            return match self.op {
+                MathOp::Multiply => Ok(ConnectionValue::Mat4(mat("a")? * mat("b")?)),
+                op => Err(anyhow!("{} is not supported for Mat4", op.name())),
+            };
+        }
+
+        let vec = |name: &str| {
+            input_value(name)
+                .as_ref()
+                .and_then(to_vec4)
+                .ok_or_else(|| missing(name))
+        };
+        let float = |name: &str| vec(name).map(|v| v.x);
+
+        let result = match self.op {
+            MathOp::Add => vec("a")? + vec("b")?,
+            MathOp::Multiply => vec("a")? * vec("b")?,
+            MathOp::Mix => vec("a")?.lerp(vec("b")?, float("t")?),
+            MathOp::Clamp => vec("value")?.clamp(vec("min")?, vec("max")?),
+            MathOp::Remap => {
+                let in_min = vec("in_min")?;
+                let out_min = vec("out_min")?;
+                let in_range = vec("in_max")? - in_min;
+                let t = (vec("value")? - in_min) / in_range;
+                //an empty input range would divide by zero, so it maps to out_min
+                let t = Vec4::select(in_range.cmpeq(Vec4::ZERO), Vec4::ZERO, t);
+                out_min + t * (vec("out_max")? - out_min)
+            }
+            MathOp::Length => Vec4::splat(vec("value")?.length()),
+            MathOp::Normalize => vec("value")?.normalize_or_zero(),
+            MathOp::Sin => {
+                let v = vec("value")?;
+                Vec4::new(v.x.sin(), v.y.sin(), v.z.sin(), v.w.sin())
+            }
+            MathOp::Cos => {
+                let v = vec("value")?;
+                Vec4::new(v.x.cos(), v.y.cos(), v.z.cos(), v.w.cos())
+            }
+            MathOp::Compose => {
+                let mut v = Vec4::ZERO;
+                for (i, name) in COMPONENTS[..component_count(self.ty)].iter().enumerate() {
+                    v[i] = float(name)?;
+                }
+                v
+            }
+            MathOp::Decompose => {
+                let component = component.ok_or_else(|| missing("component"))?;
+                Vec4::splat(vec("value")?[component.min(3)])
+            }
+        };
+
+        from_vec4(self.op.output_type(self.ty), result)
+            .ok_or_else(|| anyhow!("{} cannot output {}", self.op.name(), self.ty))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compute(
+        op: MathOp,
+        ty: ConnectionType,
+        inputs: &[(&str, ConnectionValue)],
+    ) -> anyhow::Result<ConnectionValue> {
+        let value = |name: &str| {
+            inputs
+                .iter()
+                .find(|(input_name, _)| *input_name == name)
+                .map(|(_, value)| value.clone())
+        };
+        MathNode::new(op, ty).compute(value, Some(1))
+    }
+
+    fn float(op: MathOp, inputs: &[(&str, f32)]) -> f32 {
+        let inputs = inputs
+            .iter()
+            .map(|(name, value)| (*name, ConnectionValue::Float(*value)))
+            .collect::<Vec<_>>();
+
+        match compute(op, ConnectionType::Float, &inputs).unwrap() {
+            ConnectionValue::Float(value) => value,
+            other => panic!("Expected a float, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn float_ops() {
+        assert_eq!(float(MathOp::Add, &[("a", 1.5), ("b", 2.0)]), 3.5);
+        assert_eq!(float(MathOp::Multiply, &[("a", 1.5), ("b", 2.0)]), 3.0);
+        assert_eq!(
+            float(MathOp::Mix, &[("a", 0.0), ("b", 4.0), ("t", 0.25)]),
+            1.0
+        );
+        assert_eq!(
+            float(MathOp::Clamp, &[("value", 5.0), ("min", 0.0), ("max", 2.0)]),
+            2.0
+        );
+        assert_eq!(float(MathOp::Sin, &[("value", 0.0)]), 0.0);
+        assert_eq!(float(MathOp::Cos, &[("value", 0.0)]), 1.0);
+    }
+
+    #[test]
+    fn remap() {
+        let remap = |value, in_min, in_max| {
+            float(
+                MathOp::Remap,
+                &[
+                    ("value", value),
+                    ("in_min", in_min),
+                    ("in_max", in_max),
+                    ("out_min", 10.0),
+                    ("out_max", 20.0),
+                ],
+            )
+        };
+
+        assert_eq!(remap(0.5, 0.0, 1.0), 15.0);
+        assert_eq!(remap(2.0, 0.0, 1.0), 30.0);
+        assert_eq!(remap(0.5, 1.0, 0.0), 15.0);
+    }
+
+    #[test]
+    fn remap_empty_range_is_out_min() {
+        let inputs = [
+            ("value", 3.0),
+            ("in_min", 1.0),
+            ("in_max", 1.0),
+            ("out_min", 10.0),
+            ("out_max", 20.0),
+        ];
+        assert_eq!(float(MathOp::Remap, &inputs), 10.0);
+    }
+
+    #[test]
+    fn vector_ops() {
+        let v = ConnectionValue::Vec2([3.0, 4.0]);
+
+        let length = compute(
+            MathOp::Length,
+            ConnectionType::Vec2,
+            &[("value", v.clone())],
+        );
+        assert_eq!(length.unwrap(), ConnectionValue::Float(5.0));
+
+        let normalized = compute(
+            MathOp::Normalize,
+            ConnectionType::Vec2,
+            &[("value", v.clone())],
+        );
+        assert_eq!(normalized.unwrap(), ConnectionValue::Vec2([0.6, 0.8]));
+
+        let y = compute(MathOp::Decompose, ConnectionType::Vec2, &[("value", v)]);
+        assert_eq!(y.unwrap(), ConnectionValue::Float(4.0));
+
+        let composed = compute(
+            MathOp::Compose,
+            ConnectionType::Vec2,
+            &[
+                ("x", ConnectionValue::Float(1.0)),
+                ("y", ConnectionValue::Float(2.0)),
+            ],
+        );
+        assert_eq!(composed.unwrap(), ConnectionValue::Vec2([1.0, 2.0]));
+    }
+
+    #[test]
+    fn mat4_multiply() {
+        let scale = glam::Mat4::from_scale(glam::Vec3::splat(2.0));
+        let translate = glam::Mat4::from_translation(glam::Vec3::X);
+
+        let result = compute(
+            MathOp::Multiply,
+            ConnectionType::Mat4,
+            &[
+                ("a", ConnectionValue::Mat4(translate)),
+                ("b", ConnectionValue::Mat4(scale)),
+            ],
+        );
+        assert_eq!(result.unwrap(), ConnectionValue::Mat4(translate * scale));
+
+        assert!(compute(MathOp::Add, ConnectionType::Mat4, &[]).is_err());
+    }
+
+    #[test]
+    fn missing_input() {
+        assert!(compute(MathOp::Add, ConnectionType::Float, &[]).is_err());
+    }
+}
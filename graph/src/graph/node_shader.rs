@@ -2,24 +2,50 @@ use std::rc::Rc;
 
 use glium::{
     backend::Facade,
-    framebuffer::SimpleFrameBuffer,
+    framebuffer::MultiOutputFrameBuffer,
     texture::Texture2d,
     uniforms::{AsUniformValue, UniformValue, Uniforms},
     Surface,
 };
 
-use super::{graph_utils::ProcessedInputs, node_types::NodeType, spout_out_shader::SpoutOutShader};
-use crate::{def::AsUniformOptional, textures::TextureManager};
+use super::{
+    graph_utils::ProcessedInputs, node_types::NodeType, record_out_shader::RecordOutShader,
+    spout_out_shader::SpoutOutShader,
+};
+use crate::{
+    connections::{ConnectionValue, NodeOutputs},
+    def::AsUniformOptional,
+    textures::{OutputRes, OutputSettings, TextureManager},
+};
+use common::{clock::FrameTime, texture::TextureFormat};
 use shaders::{
-    gl_expression::GlExpressionRenderer, isf::shader::IsfShader, obj_shader::renderer::ObjRenderer,
+    composite::CompositeRenderer,
+    gl_expression::GlExpressionRenderer,
+    image_shader::{
+        renderer::ImageRenderer,
+        sequence::{ImageSequence, Playback, PlaybackMode},
+    },
+    isf::shader::IsfShader,
+    obj_shader::renderer::ObjRenderer,
 };
 
+///The extra outputs of an Obj render that are connected to something
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct ObjOutputs {
+    pub depth: bool,
+    pub normals: bool,
+}
+
 /// Holds shaders for the fast rendering loop
 pub enum NodeShader {
     Isf(IsfShader),
     SpoutOut(SpoutOutShader),
-    Obj(ObjRenderer),
+    Record(RecordOutShader),
+    Obj(ObjRenderer, ObjOutputs),
     Expression(GlExpressionRenderer),
+    Composite(CompositeRenderer),
+    Image(ImageRenderer),
+    ImageSequence(ImageSequence),
 }
 
 impl NodeShader {
@@ -31,7 +57,11 @@ impl NodeShader {
                     .map(NodeShader::Isf),
             ),
             NodeType::SharedOut => Some(Ok(NodeShader::SpoutOut(SpoutOutShader::new()))),
-            NodeType::ObjRender => Some(Ok(NodeShader::Obj(ObjRenderer::new(facade).unwrap()))),
+            NodeType::Record => Some(Ok(NodeShader::Record(RecordOutShader::new()))),
+            NodeType::ObjRender => Some(Ok(NodeShader::Obj(
+                ObjRenderer::new(facade).unwrap(),
+                ObjOutputs::default(),
+            ))),
             NodeType::Expression { source: text, .. } => {
                 let mut renderer = GlExpressionRenderer::new(facade);
                 if !text.is_empty() {
@@ -41,6 +71,77 @@ impl NodeShader {
                 }
                 Some(Ok(NodeShader::Expression(renderer)))
             }
+            NodeType::Composite { layers } => Some(
+                CompositeRenderer::new(facade, *layers)
+                    .map_err(anyhow::Error::new)
+                    .map(NodeShader::Composite),
+            ),
+            NodeType::Image => Some(
+                ImageRenderer::new(facade)
+                    .map_err(anyhow::Error::new)
+                    .map(NodeShader::Image),
+            ),
+            NodeType::ImageSequence => Some(
+                ImageSequence::new(facade)
+                    .map_err(anyhow::Error::new)
+                    .map(NodeShader::ImageSequence),
+            ),
+            NodeType::Math { .. }
+            | NodeType::Feedback
+            | NodeType::Subgraph { .. }
+            | NodeType::SubgraphInput { .. }
+            | NodeType::SubgraphOutput { .. } => None,
+        }
+    }
+
+    ///If the output may change every frame, even when the inputs stay the same
+    pub fn is_time_dependent(&self) -> bool {
+        match self {
+            NodeShader::Isf(isf) => isf.is_time_dependent(),
+            NodeShader::SpoutOut(_) | NodeShader::Record(_) | NodeShader::ImageSequence(_) => true,
+            NodeShader::Obj(..)
+            | NodeShader::Expression(_)
+            | NodeShader::Composite(_)
+            | NodeShader::Image(_) => false,
+        }
+    }
+
+    ///What the node is doing, to show on it
+    pub fn status(&self) -> Option<String> {
+        match self {
+            NodeShader::Record(record) => record
+                .destination()
+                .map(|path| format!("Recording to {}", path.display())),
+            _ => None,
+        }
+    }
+
+    ///Match internal buffers and projection to the output size
+    pub fn resize(&mut self, res: (u32, u32)) {
+        match self {
+            NodeShader::Isf(isf) => isf.set_res(res),
+            NodeShader::Obj(obj, _) => obj.set_res(res),
+            NodeShader::SpoutOut(_)
+            | NodeShader::Record(_)
+            | NodeShader::Expression(_)
+            | NodeShader::Composite(_)
+            | NodeShader::Image(_)
+            | NodeShader::ImageSequence(_) => {}
+        }
+    }
+
+    ///Set from the names of the outputs that are connected, so unused ones aren't drawn.
+    /// Returns true if the node has to render again
+    pub fn set_connected_outputs(&mut self, names: &[&str]) -> bool {
+        match self {
+            NodeShader::Obj(_, outputs) => {
+                let connected = ObjOutputs {
+                    depth: names.contains(&"depth"),
+                    normals: names.contains(&"normals"),
+                };
+                std::mem::replace(outputs, connected) != connected
+            }
+            _ => false,
         }
     }
 
@@ -48,9 +149,14 @@ impl NodeShader {
         &mut self,
         facade: &impl Facade,
         textures: &mut TextureManager,
+        settings: &OutputSettings,
+        time: &FrameTime,
         inputs: impl UniformsExt,
-    ) -> anyhow::Result<Rc<Texture2d>> {
-        let color: Rc<Texture2d> = textures.get_color(facade);
+    ) -> anyhow::Result<NodeOutputs> {
+        let color: Rc<Texture2d> = textures.get_color_with(facade, settings);
+        self.resize(color.dimensions());
+
+        let mut named = vec![];
 
         match self {
             NodeShader::Expression(renderer) => {
@@ -58,18 +164,87 @@ impl NodeShader {
                 surface.clear_color(0.0, 0.0, 0.0, 0.0);
                 renderer.draw(&mut surface, &inputs)?;
             }
+            NodeShader::Composite(composite) => {
+                let connected = inputs.texture_names();
+                let mut surface = color.as_surface();
+                surface.clear_color(0.0, 0.0, 0.0, 0.0);
+                composite.draw(&mut surface, &inputs, &connected)?;
+            }
+            NodeShader::Image(image) => {
+                let mut surface = color.as_surface();
+                surface.clear_color(0.0, 0.0, 0.0, 0.0);
+                image.draw(&mut surface, &inputs)?;
+            }
+            NodeShader::ImageSequence(sequence) => {
+                let playback = Playback {
+                    fps: inputs.float("fps").unwrap_or(30.0),
+                    speed: inputs.float("speed").unwrap_or(1.0),
+                    position: inputs.float("position").unwrap_or_default(),
+                    mode: inputs
+                        .int("mode")
+                        .and_then(|mode| PlaybackMode::ALL.get(mode as usize))
+                        .copied()
+                        .unwrap_or(PlaybackMode::Loop),
+                };
+
+                let mut surface = color.as_surface();
+                surface.clear_color(0.0, 0.0, 0.0, 0.0);
+                sequence.draw(facade, &mut surface, &inputs, time.time, &playback)?;
+            }
             NodeShader::Isf(isf) => {
+                let audio: Vec<_> = isf
+                    .audio_inputs()
+                    .iter()
+                    .filter_map(|input| {
+                        let texture = textures.audio.texture(facade, time, input)?;
+                        Some((input.name.clone(), texture))
+                    })
+                    .collect();
+                let uniforms = AudioUniforms {
+                    audio: &audio,
+                    inner: &inputs,
+                };
+
                 let mut surface = color.as_surface();
                 surface.clear_color(0.0, 0.0, 0.0, 0.0);
-                isf.draw(&mut surface, &inputs)?;
+                isf.draw(facade, &mut surface, &uniforms, time)?;
+
+                named.extend(isf.pass_textures().map(|(name, texture)| {
+                    (
+                        name.to_string(),
+                        Some(ConnectionValue::Texture2D(texture.clone())),
+                    )
+                }));
             }
-            NodeShader::Obj(obj) => {
-                let depth = textures.get_depth(facade);
+            NodeShader::Obj(obj, outputs) => {
+                let (width, height) = color.dimensions();
+                //always needed to hide the back of the model
+                let depth = textures.get_depth(facade, (width, height));
+
+                //stored as color so they can be sampled by other nodes
+                let buffer_settings = OutputSettings {
+                    res: OutputRes::Absolute(width, height),
+                    format: TextureFormat::Rgba16F,
+                };
+                let depth_color = outputs
+                    .depth
+                    .then(|| textures.get_color_with(facade, &buffer_settings));
+                let normals = outputs
+                    .normals
+                    .then(|| textures.get_color_with(facade, &buffer_settings));
+
+                let attachments: Vec<_> = std::iter::once(("out_color", color.as_ref()))
+                    .chain(depth_color.as_deref().map(|tex| ("out_depth", tex)))
+                    .chain(normals.as_deref().map(|tex| ("out_normal", tex)))
+                    .collect();
                 let mut fb =
-                    SimpleFrameBuffer::with_depth_buffer(facade, color.as_ref(), depth.as_ref())
+                    MultiOutputFrameBuffer::with_depth_buffer(facade, attachments, depth.as_ref())
                         .unwrap();
                 fb.clear_color_and_depth((0.0, 0.0, 0.0, 0.0), f32::INFINITY);
-                obj.draw(&mut fb, &inputs)?;
+                obj.draw(&mut fb, &inputs, time)?;
+
+                named.push(("depth".to_string(), depth_color.map(Into::into)));
+                named.push(("normals".to_string(), normals.map(Into::into)));
             }
             NodeShader::SpoutOut(spout_out) => {
                 //only send if input exists
@@ -81,32 +256,45 @@ impl NodeShader {
                     spout_out.send(&color);
                 }
             }
+            NodeShader::Record(record) => {
+                if let Some(in_tex) = inputs.first_texture() {
+                    in_tex.as_surface().fill(
+                        &color.as_surface(),
+                        glium::uniforms::MagnifySamplerFilter::Linear,
+                    );
+                    record.send(&color, time.time)?;
+                }
+            }
         };
 
-        Ok(color)
+        Ok(NodeOutputs {
+            main: color.into(),
+            named,
+        })
     }
 }
 
 pub struct ProcessedShaderNodeInputs<'a, C, V> {
-    pub node_inputs: &'a ProcessedInputs<'a, C, V, Rc<Texture2d>>,
+    pub node_inputs: &'a ProcessedInputs<'a, C, V, ConnectionValue>,
 }
 
 impl<C, V> ProcessedShaderNodeInputs<'_, C, V> {
     pub fn first_texture(&self) -> Option<&Texture2d> {
         self.node_inputs
             .iter()
-            .filter_map(|(_, _, tex)| tex.as_ref().map(Rc::as_ref))
+            .filter_map(|(_, _, value)| value.as_ref().and_then(ConnectionValue::texture))
+            .map(Rc::as_ref)
             .next()
     }
 }
 
 impl<'a, C, V: AsUniformOptional> Uniforms for ProcessedShaderNodeInputs<'a, C, V> {
     fn visit_values<'b, F: FnMut(&str, UniformValue<'b>)>(&'b self, mut output: F) {
-        for (name, input, tex) in self.node_inputs {
-            let option_val = tex
+        for (name, input, value) in self.node_inputs {
+            //connected values take precedence over the constant
+            let option_val = value
                 .as_ref()
-                .map(Rc::as_ref)
-                .map(Texture2d::as_uniform_value)
+                .and_then(ConnectionValue::as_uniform_optional)
                 .or_else(|| input.value.as_uniform_optional());
 
             if let Some(val) = option_val {
@@ -116,6 +304,21 @@ impl<'a, C, V: AsUniformOptional> Uniforms for ProcessedShaderNodeInputs<'a, C,
     }
 }
 
+///ISF audio textures by input name, along with the node inputs
+struct AudioUniforms<'a, U: Uniforms> {
+    audio: &'a [(String, Rc<Texture2d>)],
+    inner: &'a U,
+}
+
+impl<U: Uniforms> Uniforms for AudioUniforms<'_, U> {
+    fn visit_values<'b, F: FnMut(&str, UniformValue<'b>)>(&'b self, mut output: F) {
+        for (name, texture) in self.audio {
+            output(name, texture.as_ref().as_uniform_value());
+        }
+        self.inner.visit_values(output);
+    }
+}
+
 pub trait UniformsExt: Uniforms {
     fn first_texture(&self) -> Option<&Texture2d> {
         let mut texture = None;
@@ -129,14 +332,49 @@ pub trait UniformsExt: Uniforms {
 
         texture
     }
+
+    fn float(&self, name: &str) -> Option<f32> {
+        let mut float = None;
+
+        self.visit_values(|uniform_name, value| match value {
+            UniformValue::Float(value) if uniform_name == name => float = Some(value),
+            _ => {}
+        });
+
+        float
+    }
+
+    fn int(&self, name: &str) -> Option<i32> {
+        let mut int = None;
+
+        self.visit_values(|uniform_name, value| match value {
+            UniformValue::SignedInt(value) if uniform_name == name => int = Some(value),
+            _ => {}
+        });
+
+        int
+    }
+
+    ///Names of the textures that are set
+    fn texture_names(&self) -> Vec<String> {
+        let mut names = vec![];
+
+        self.visit_values(|name, value| {
+            if let UniformValue::Texture2d(..) = value {
+                names.push(name.to_string());
+            }
+        });
+
+        names
+    }
 }
 
 impl<T: Uniforms> UniformsExt for T {}
 
-impl<'a, C, V> From<&'a ProcessedInputs<'a, C, V, Rc<Texture2d>>>
+impl<'a, C, V> From<&'a ProcessedInputs<'a, C, V, ConnectionValue>>
     for ProcessedShaderNodeInputs<'a, C, V>
 {
-    fn from(inputs: &'a ProcessedInputs<'a, C, V, Rc<Texture2d>>) -> Self {
+    fn from(inputs: &'a ProcessedInputs<'a, C, V, ConnectionValue>) -> Self {
         ProcessedShaderNodeInputs {
             node_inputs: inputs,
         }
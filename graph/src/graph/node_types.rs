@@ -1,6 +1,6 @@
 use common::files::map_file_tree;
 use common::tree::{BranchIndex, LeafIndex, Tree, TreeStructure};
-use egui_node_graph::{NodeTemplateIter};
+use egui_node_graph::{NodeId, NodeTemplateIter};
 use glam::Mat4;
 use serde::{Deserialize, Serialize};
 use slotmap::SlotMap;
@@ -8,24 +8,58 @@ use std::fmt::Display;
 use std::path::{Path, PathBuf};
 
 use crate::common::mat4_animator::Mat4Animator;
-use shaders::isf::meta::{default_isf_path, IsfInfo};
+use crate::graph::node_math::MathOp;
+use crate::graph::record_out_shader::RecordFormat;
+use crate::graph::subgraph::SubgraphDef;
+use shaders::composite::{blend_name, layer_name, mask_name, opacity_name, BlendMode};
+use shaders::image_shader::{renderer::ImageFit, sequence::PlaybackMode};
+use shaders::isf::meta::{default_isf_paths, IsfInfo};
 
 use crate::common::connections::{ConnectionType, InputDef, OutputDef};
-use crate::common::def::{TextStyle, UiValue};
+use crate::common::def::{RangedData, TextStyle, UiValue};
 
 ///Enum of node types used to create an actual node
-#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum NodeType {
     SharedOut,
+    ///Writes its input to disk while recording
+    Record,
     ObjRender,
+    ///Loads an image file, reloading it when it changes
+    Image,
+    ///Plays a folder of numbered image files in time with the graph
+    ImageSequence,
+    ///Outputs the texture that was connected to its input on the previous frame
+    Feedback,
     Isf {
         info: IsfInfo,
     },
+    ///Blends texture layers with an opacity, blend mode and mask each
+    Composite {
+        layers: usize,
+    },
     Expression {
         inputs: Option<Vec<InputDef>>,
         name: String,
         source: String,
     },
+    Math {
+        op: MathOp,
+        ty: ConnectionType,
+    },
+    ///A graph that is rendered as a single node
+    Subgraph {
+        name: String,
+        def: SubgraphDef,
+    },
+    ///Receives an input of the containing subgraph node
+    SubgraphInput {
+        ty: ConnectionType,
+    },
+    ///Sends its input out of the containing subgraph node
+    SubgraphOutput {
+        ty: ConnectionType,
+    },
 }
 
 pub trait GetTemplate {
@@ -40,10 +74,58 @@ impl Display for NodeType {
 }
 
 impl NodeType {
+    ///Would create the same kind of node, for numbering copies.
+    /// Subgraphs only compare their name and ports, as their inner graphs can't be compared
+    pub fn same_template(&self, other: &Self) -> bool {
+        match (self, other) {
+            (NodeType::Isf { info }, NodeType::Isf { info: other }) => info == other,
+            (NodeType::Composite { layers }, NodeType::Composite { layers: other }) => {
+                layers == other
+            }
+            (
+                NodeType::Expression {
+                    inputs,
+                    name,
+                    source,
+                },
+                NodeType::Expression {
+                    inputs: other_inputs,
+                    name: other_name,
+                    source: other_source,
+                },
+            ) => inputs == other_inputs && name == other_name && source == other_source,
+            (
+                NodeType::Math { op, ty },
+                NodeType::Math {
+                    op: other_op,
+                    ty: other_ty,
+                },
+            ) => op == other_op && ty == other_ty,
+            (
+                NodeType::Subgraph { name, def },
+                NodeType::Subgraph {
+                    name: other_name,
+                    def: other_def,
+                },
+            ) => name == other_name && def.same_ports(other_def),
+            (NodeType::SubgraphInput { ty }, NodeType::SubgraphInput { ty: other })
+            | (NodeType::SubgraphOutput { ty }, NodeType::SubgraphOutput { ty: other }) => {
+                ty == other
+            }
+            //the rest have no fields
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+
     pub fn get_name(&self) -> &str {
         match self {
             NodeType::SharedOut => "SpoutOut",
+            NodeType::Record => "Record",
             NodeType::ObjRender => "ObjRender",
+            NodeType::Image => "Image",
+            NodeType::ImageSequence => "ImageSequence",
+            NodeType::Feedback => "Feedback",
+            NodeType::Composite { .. } => "Composite",
             NodeType::Isf { info } => info.name.as_str(),
             NodeType::Expression { name, .. } => {
                 if name.is_empty() {
@@ -52,6 +134,10 @@ impl NodeType {
                     &name
                 }
             }
+            NodeType::Math { op, .. } => op.name(),
+            NodeType::Subgraph { name, .. } => name.as_str(),
+            NodeType::SubgraphInput { .. } => "Input",
+            NodeType::SubgraphOutput { .. } => "Output",
         }
     }
 
@@ -65,6 +151,37 @@ impl NodeType {
         match self {
             NodeType::Isf { info } => info.def.inputs.iter().map(InputDef::from).collect(),
             NodeType::SharedOut => vec![("name", "RustSpout").into(), InputDef::texture("texture")],
+            NodeType::Record => vec![
+                InputDef::texture("texture"),
+                ("record", UiValue::Bool(false.into())).into(),
+                menu_input("format", RecordFormat::ALL.iter().map(RecordFormat::name)),
+                ("fps", ranged_float(30.0, 1.0, 120.0)).into(),
+                ("folder", UiValue::Folder(None)).into(),
+                ("pipe", UiValue::Path(None)).into(),
+            ],
+            NodeType::Feedback => vec![InputDef::texture("texture")],
+            NodeType::Image => vec![
+                ("path", UiValue::Path(None)).into(),
+                menu_input("fit", ImageFit::ALL.iter().map(ImageFit::name)),
+            ],
+            NodeType::ImageSequence => vec![
+                ("path", UiValue::Folder(None)).into(),
+                ("fps", ranged_float(30.0, 1.0, 120.0)).into(),
+                ("speed", ranged_float(1.0, -4.0, 4.0)).into(),
+                ("position", ranged_float(0.0, 0.0, 1.0)).into(),
+                menu_input("mode", PlaybackMode::ALL.iter().map(PlaybackMode::name)),
+                menu_input("fit", ImageFit::ALL.iter().map(ImageFit::name)),
+            ],
+            NodeType::Composite { layers } => (0..*layers)
+                .flat_map(|i| {
+                    [
+                        InputDef::texture(layer_name(i)),
+                        (opacity_name(i), ranged_float(1.0, 0.0, 1.0)).into(),
+                        menu_input(blend_name(i), BlendMode::ALL.iter().map(BlendMode::name)),
+                        InputDef::texture(mask_name(i)),
+                    ]
+                })
+                .collect(),
             NodeType::ObjRender => vec![
                 ("obj", UiValue::Path(None)).into(),
                 ("model", UiValue::Mat4(Mat4::IDENTITY.into())).into(),
@@ -86,29 +203,123 @@ impl NodeType {
                     .into(),
                 InputDef::texture("pixels"),
             ],
+            NodeType::Math { op, ty } => op.input_types(*ty),
+            NodeType::Subgraph { def, .. } => def.input_types(),
+            NodeType::SubgraphInput { .. } => vec![],
+            NodeType::SubgraphOutput { ty } => vec![InputDef {
+                name: "value".to_string(),
+                ty: *ty,
+                value: ty.default_value(),
+            }],
         }
     }
 
     pub fn get_output_types(&self) -> Vec<OutputDef> {
         match self {
-            NodeType::SharedOut => vec![],
-            NodeType::Isf { .. } => vec![ConnectionType::Texture2D.into()],
-            NodeType::ObjRender => vec![ConnectionType::Texture2D.into()],
+            NodeType::SharedOut | NodeType::Record => vec![],
+            NodeType::Isf { info } => {
+                //each pass with a target can be used on its own
+                let passes = info.def.passes.iter().filter_map(|pass| {
+                    let target = pass.target.as_ref()?;
+                    Some((target.as_str(), ConnectionType::Texture2D).into())
+                });
+
+                std::iter::once(ConnectionType::Texture2D.into())
+                    .chain(passes)
+                    .collect()
+            }
+            NodeType::ObjRender => vec![
+                ConnectionType::Texture2D.into(),
+                ("depth", ConnectionType::Texture2D).into(),
+                ("normals", ConnectionType::Texture2D).into(),
+            ],
+            NodeType::Feedback => vec![ConnectionType::Texture2D.into()],
+            NodeType::Composite { .. } => vec![ConnectionType::Texture2D.into()],
+            NodeType::Image => vec![ConnectionType::Texture2D.into()],
+            NodeType::ImageSequence => vec![ConnectionType::Texture2D.into()],
             NodeType::Expression { .. } => vec![ConnectionType::Texture2D.into()], // _ => vec![ConnectionType::Texture2D.into()],
+            NodeType::Math { op, ty } => vec![op.output_type(*ty).into()],
+            NodeType::Subgraph { def, .. } => def.output_types(),
+            NodeType::SubgraphInput { ty } => vec![(*ty).into()],
+            NodeType::SubgraphOutput { .. } => vec![],
+        }
+    }
+
+    ///If the node renders into a texture it allocates
+    pub fn allocates_texture(&self) -> bool {
+        matches!(
+            self,
+            NodeType::Isf { .. }
+                | NodeType::ObjRender
+                | NodeType::Composite { .. }
+                | NodeType::Image
+                | NodeType::ImageSequence
+                | NodeType::Expression { .. }
+                | NodeType::SharedOut
+                | NodeType::Record
+        )
+    }
+
+    ///Add the inputs and outputs of this type to a node
+    pub fn build_node<N, V: From<UiValue>>(
+        &self,
+        graph: &mut egui_node_graph::Graph<N, ConnectionType, V>,
+        node_id: NodeId,
+    ) {
+        for input in self.get_input_types() {
+            if let Some(kind) = input.kind() {
+                graph.add_input_param(
+                    node_id,
+                    input.name,
+                    input.ty,
+                    input.value.into(),
+                    kind,
+                    true,
+                );
+            }
+        }
+
+        for output in self.get_output_types() {
+            graph.add_output_param(node_id, output.name, output.ty);
         }
     }
 }
 
+fn ranged_float(value: f32, min: f32, max: f32) -> UiValue {
+    UiValue::Float(RangedData {
+        value,
+        min: Some(min),
+        max: Some(max),
+        default: Some(value),
+    })
+}
+
+///Options are numbered in order, starting with the default
+fn menu_input<'a>(name: impl Into<String>, options: impl Iterator<Item = &'a str>) -> InputDef {
+    let mapping = options
+        .enumerate()
+        .map(|(index, option)| (option.to_string(), index as i32))
+        .collect();
+
+    (name, UiValue::Menu(RangedData::from(0), mapping)).into()
+}
+
+///The bundled shaders and the platform ISF library folders
 pub fn default_isf_dirs() -> Vec<PathBuf> {
-    vec![
-        Path::new(env!("CARGO_MANIFEST_DIR")).join("isf_shaders"),
-        default_isf_path(),
-    ]
+    std::iter::once(Path::new(env!("CARGO_MANIFEST_DIR")).join("isf_shaders"))
+        .chain(default_isf_paths())
+        .collect()
 }
 
 impl NodeType {
-    pub fn templates() -> Tree<String, NodeType> {
-        let isf_paths = default_isf_dirs();
+    ///Every node that can be created. ISF shaders are searched for in the default dirs and extra_isf_dirs
+    pub fn templates(extra_isf_dirs: &[PathBuf]) -> Tree<String, NodeType> {
+        let mut isf_paths = default_isf_dirs();
+        for dir in extra_isf_dirs {
+            if !isf_paths.contains(dir) {
+                isf_paths.push(dir.clone());
+            }
+        }
 
         let mut branches: SlotMap<BranchIndex, String> = SlotMap::default();
         let mut leaves: SlotMap<LeafIndex, NodeType> = SlotMap::default();
@@ -150,6 +361,43 @@ impl NodeType {
             })
             .collect();
 
+        let mut math_branches: Vec<(ConnectionType, Vec<TreeStructure>)> = vec![];
+        for (op, ty) in MathOp::all() {
+            let leaf = TreeStructure::Leaf(leaves.insert(NodeType::Math { op, ty }));
+
+            match math_branches.iter_mut().find(|(branch_ty, _)| *branch_ty == ty) {
+                Some((_, branch)) => branch.push(leaf),
+                None => math_branches.push((ty, vec![leaf])),
+            }
+        }
+
+        let math = math_branches
+            .into_iter()
+            .map(|(ty, leaves)| {
+                TreeStructure::Branch(branches.insert(ty.to_string().to_lowercase()), leaves)
+            })
+            .collect();
+
+        let composites = [2, 4, 8]
+            .into_iter()
+            .map(|layers| TreeStructure::Leaf(leaves.insert(NodeType::Composite { layers })))
+            .collect();
+
+        let port_types = [
+            ConnectionType::Texture2D,
+            ConnectionType::Float,
+            ConnectionType::Vec2,
+            ConnectionType::Vec4,
+            ConnectionType::Color,
+            ConnectionType::Mat4,
+        ];
+
+        let subgraph = port_types
+            .into_iter()
+            .flat_map(|ty| [NodeType::SubgraphInput { ty }, NodeType::SubgraphOutput { ty }])
+            .map(|node| TreeStructure::Leaf(leaves.insert(node)))
+            .collect();
+
         let defaults = Self::defaults()
             .into_iter()
             .map(|node| TreeStructure::Leaf(leaves.insert(node)))
@@ -160,6 +408,9 @@ impl NodeType {
                 TreeStructure::Branch(branches.insert("defaults".to_string()), defaults),
                 TreeStructure::Branch(branches.insert("isf".to_string()), isf_templates),
                 TreeStructure::Branch(branches.insert("expressions".to_string()), expressions),
+                TreeStructure::Branch(branches.insert("math".to_string()), math),
+                TreeStructure::Branch(branches.insert("composite".to_string()), composites),
+                TreeStructure::Branch(branches.insert("subgraph".to_string()), subgraph),
             ],
             branches,
             leaves,
@@ -169,7 +420,16 @@ impl NodeType {
     pub fn defaults() -> Vec<NodeType> {
         let types = vec![
             NodeType::ObjRender,
+            NodeType::Image,
+            NodeType::ImageSequence,
             NodeType::SharedOut,
+            NodeType::Record,
+            NodeType::Feedback,
+            NodeType::Composite { layers: 2 },
+            NodeType::Subgraph {
+                name: "Subgraph".to_string(),
+                def: SubgraphDef::new(),
+            },
             NodeType::Expression {
                 inputs: None,
                 name: String::default(),
@@ -1,6 +1,11 @@
 
 use super::graph_change_listener::{GraphChangeEvent, GraphUpdateListener};
-use super::{graph_utils::InputParams, node_shader::NodeShader, node_types::NodeType};
+use super::{
+    graph_utils::InputParams,
+    node_shader::NodeShader,
+    node_types::NodeType,
+    record_out_shader::{RecordFormat, RecordSettings},
+};
 use crate::common::def::UiValue;
 
 use crate::def::GetUiValue;
@@ -9,10 +14,13 @@ use crate::GetTemplate;
 use egui_node_graph::NodeId;
 use glium::backend::Facade;
 use shaders::{
-    gl_expression::GlExpressionUpdater, isf::updater::IsfUpdater, obj_shader::loader::ObjLoader,
+    gl_expression::GlExpressionUpdater,
+    image_shader::loader::{ImageLoader, SequenceLoader},
+    isf::updater::IsfUpdater,
+    obj_shader::loader::ObjLoader,
 };
 use slotmap::{SecondaryMap, SparseSecondaryMap};
-use std::time::{SystemTime};
+use std::{collections::HashSet, path::Path, time::SystemTime};
 
 #[derive(Default)]
 pub struct NodeUpdaters {
@@ -25,6 +33,7 @@ impl NodeUpdaters {
         shaders: &mut SecondaryMap<NodeId, NodeShader>,
         graph: &mut egui_node_graph::Graph<N, C, V>,
         facade: &impl Facade,
+        dirty: &mut HashSet<NodeId>,
     ) -> SparseSecondaryMap<NodeId, anyhow::Error> {
         let mut errors = SparseSecondaryMap::default();
 
@@ -37,10 +46,14 @@ impl NodeUpdaters {
                 .collect();
 
             if let Some(shader) = shaders.get_mut(node_id) {
-                if let Err(err) =
-                    updater.update(facade, node.user_data.template_mut(), &inputs, shader)
-                {
-                    errors.insert(node_id, err);
+                match updater.update(facade, node.user_data.template_mut(), &inputs, shader) {
+                    Ok(true) => {
+                        dirty.insert(node_id);
+                    }
+                    Ok(false) => {}
+                    Err(err) => {
+                        errors.insert(node_id, err);
+                    }
                 }
             }
         }
@@ -78,6 +91,12 @@ impl<N: GetTemplate, C, V> GraphUpdateListener<N, C, V> for NodeUpdaters {
 pub enum UpdateShader {
     Isf(IsfUpdater),
     Obj(ObjLoader),
+    Image(ImageLoader),
+    Sequence(SequenceLoader),
+    ///Starts and stops recording from the node params
+    Record,
+    ///Publishes under the name param
+    SharedOut,
     Expression(GlExpressionUpdater),
 }
 
@@ -91,6 +110,10 @@ impl UpdateShader {
                 modified: SystemTime::now(),
             })),
             NodeType::ObjRender => Some(Self::Obj(ObjLoader::new())),
+            NodeType::Image => Some(Self::Image(ImageLoader::new())),
+            NodeType::ImageSequence => Some(Self::Sequence(SequenceLoader::new())),
+            NodeType::Record => Some(Self::Record),
+            NodeType::SharedOut => Some(Self::SharedOut),
             NodeType::Expression { source: text, .. } => {
                 Some(Self::Expression(GlExpressionUpdater {
                     frag_source: Some(text.clone()),
@@ -100,33 +123,81 @@ impl UpdateShader {
         }
     }
 
+    ///Returns true if the shader was changed
     pub fn update<C, V: GetUiValue>(
         &mut self,
         facade: &impl Facade,
         template: &mut NodeType,
         inputs: &InputParams<'_, C, V>,
         shader: &mut NodeShader,
-    ) -> anyhow::Result<()> {
-        match (self, template, shader) {
+    ) -> anyhow::Result<bool> {
+        let changed = match (self, template, shader) {
             (
                 UpdateShader::Isf(updater),
                 NodeType::Isf { info: isf_info },
                 NodeShader::Isf(shader),
             ) => {
-                updater.reload_if_updated(facade, isf_info, shader)?;
+                updater.reload_if_updated(facade, isf_info, shader)?
+            }
+
+            (UpdateShader::Obj(loader), _, NodeShader::Obj(obj_renderer, _)) => {
+                match path_input(inputs, "obj") {
+                    Some(path) => loader.load_if_changed(facade, path, obj_renderer)?,
+                    None => false,
+                }
+            }
+
+            (UpdateShader::Image(loader), _, NodeShader::Image(image_renderer)) => {
+                match path_input(inputs, "path") {
+                    Some(path) => loader.load_if_changed(facade, path, image_renderer)?,
+                    None => false,
+                }
+            }
+
+            (UpdateShader::Sequence(loader), _, NodeShader::ImageSequence(sequence)) => {
+                match path_input(inputs, "path") {
+                    Some(path) => loader.load_if_changed(path, sequence)?,
+                    None => false,
+                }
             }
 
-            (UpdateShader::Obj(loader), _, NodeShader::Obj(obj_renderer)) => {
-                if let Some(Some(path)) =
+            (UpdateShader::Record, _, NodeShader::Record(record)) => {
+                let value = |name: &str| {
                     inputs
                         .iter()
-                        .find_map(|(_name, input)| match &input.value.ui_value() {
-                            UiValue::Path(path) => Some(path),
-                            _ => None,
-                        })
-                {
-                    loader.load_if_changed(facade, &path, obj_renderer)?;
-                }
+                        .find(|(input_name, _)| *input_name == name)
+                        .map(|(_, input)| input.value.ui_value())
+                };
+
+                let format = match value("format") {
+                    Some(UiValue::Menu(data, _)) => RecordFormat::ALL.get(data.value as usize),
+                    _ => None,
+                };
+                let fps = match value("fps") {
+                    Some(UiValue::Float(data)) => data.value,
+                    _ => 30.0,
+                };
+                let recording = matches!(value("record"), Some(UiValue::Bool(data)) if data.value);
+
+                let settings = RecordSettings {
+                    format: format.copied().unwrap_or(RecordFormat::Png),
+                    fps,
+                    folder: path_input(inputs, "folder").map(Path::to_path_buf),
+                    pipe: path_input(inputs, "pipe").map(Path::to_path_buf),
+                };
+
+                record.update(settings, recording)?;
+                false
+            }
+
+            (UpdateShader::SharedOut, _, NodeShader::SpoutOut(spout_out)) => {
+                let name = inputs.iter().find_map(|(name, input)| match input.value.ui_value() {
+                    UiValue::Text(text, _) if *name == "name" => Some(text.value.as_str()),
+                    _ => None,
+                });
+
+                spout_out.set_name(name.unwrap_or_default())?;
+                false
             }
 
             (
@@ -141,12 +212,28 @@ impl UpdateShader {
                         None
                     }
                 }) {
-                    let _inputs = updater.update(facade, renderer, frag_source)?;
+                    updater.update(facade, renderer, frag_source)?
+                } else {
+                    false
                 }
             }
-            _ => {}
-        }
+            _ => false,
+        };
 
-        Ok(())
+        Ok(changed)
     }
 }
+
+///The path chosen for a file or folder input, if there is one
+fn path_input<'a, C, V: GetUiValue>(
+    inputs: &InputParams<'a, C, V>,
+    name: &str,
+) -> Option<&'a Path> {
+    inputs
+        .iter()
+        .find(|(input_name, _)| *input_name == name)
+        .and_then(|(_, input)| match input.value.ui_value() {
+            UiValue::Path(path) | UiValue::Folder(path) => path.as_deref(),
+            _ => None,
+        })
+}
@@ -0,0 +1,289 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::mpsc::{sync_channel, SyncSender, TrySendError},
+    thread::{self, JoinHandle},
+};
+
+use anyhow::{anyhow, bail};
+use glium::Texture2d;
+use image::RgbaImage;
+
+use crate::textures::readback::TextureReadback;
+
+///Frames waiting to be written before new ones are dropped
+const FRAME_QUEUE: usize = 8;
+
+const DEFAULT_FOLDER: &str = "recordings";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RecordFormat {
+    ///A numbered png for each frame
+    Png,
+    ///Uncompressed yuv 4:4:4 stream, can be piped into ffmpeg
+    Y4m,
+}
+
+impl RecordFormat {
+    ///In the order of the format menu values
+    pub const ALL: [RecordFormat; 2] = [RecordFormat::Png, RecordFormat::Y4m];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RecordFormat::Png => "png",
+            RecordFormat::Y4m => "y4m",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordSettings {
+    pub format: RecordFormat,
+    ///Frames written per second of graph time, fewer than rendered if lower than the render rate
+    pub fps: f32,
+    ///A timestamped folder is created in here for each recording, recordings/ if None
+    pub folder: Option<PathBuf>,
+    ///Write the y4m stream here instead, such as a named pipe that ffmpeg reads
+    pub pipe: Option<PathBuf>,
+}
+
+struct Recording {
+    ///Folder of pngs or the y4m file being written
+    destination: PathBuf,
+    frames: SyncSender<RgbaImage>,
+    writer: JoinHandle<anyhow::Result<()>>,
+    ///Seconds of graph time between written frames
+    interval: f32,
+    ///Graph time of the next frame to write
+    next_time: Option<f32>,
+    ///The last frame, queued once the gpu has copied it
+    pending: Option<TextureReadback>,
+}
+
+///Writes its input to disk while recording, the counterpart of [super::spout_out_shader::SpoutOutShader]
+pub struct RecordOutShader {
+    settings: RecordSettings,
+    recording: Option<Recording>,
+    ///Writers of stopped recordings that are still writing their queued frames.
+    /// A pipe without a reader never finishes, so they aren't waited for
+    finishing: Vec<JoinHandle<anyhow::Result<()>>>,
+}
+
+impl RecordOutShader {
+    pub fn new() -> Self {
+        Self {
+            settings: RecordSettings {
+                format: RecordFormat::Png,
+                fps: 30.0,
+                folder: None,
+                pipe: None,
+            },
+            recording: None,
+            finishing: Vec::new(),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    ///Where the current recording is being written
+    pub fn destination(&self) -> Option<&Path> {
+        self.recording
+            .as_ref()
+            .map(|recording| recording.destination.as_path())
+    }
+
+    ///Start or stop recording. Settings apply from the next recording.
+    /// Also reports errors from recordings that have finished writing
+    pub fn update(&mut self, settings: RecordSettings, record: bool) -> anyhow::Result<()> {
+        self.settings = settings;
+
+        match (record, self.is_recording()) {
+            (true, false) => self.start()?,
+            (false, true) => self.stop(),
+            _ => {}
+        }
+
+        self.poll_finished()
+    }
+
+    ///A new timestamped folder in the recordings folder
+    fn create_dir(&self) -> std::io::Result<PathBuf> {
+        let timestamp = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S");
+        let folder = self
+            .settings
+            .folder
+            .as_deref()
+            .unwrap_or_else(|| Path::new(DEFAULT_FOLDER));
+        let dir = folder.join(timestamp.to_string());
+        std::fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    fn start(&mut self) -> anyhow::Result<()> {
+        let (frames, received) = sync_channel::<RgbaImage>(FRAME_QUEUE);
+        let format = self.settings.format;
+        let fps = self.settings.fps.max(1.0);
+
+        //a pipe is only written by y4m recordings, which then don't need a folder
+        let destination = match (format, &self.settings.pipe) {
+            (RecordFormat::Png, _) => self.create_dir()?,
+            (RecordFormat::Y4m, Some(pipe)) => pipe.clone(),
+            (RecordFormat::Y4m, None) => self.create_dir()?.join("recording.y4m"),
+        };
+        let out_path = destination.clone();
+
+        let writer = thread::spawn(move || -> anyhow::Result<()> {
+            match format {
+                RecordFormat::Png => {
+                    for (index, frame) in received.into_iter().enumerate() {
+                        frame.save(out_path.join(format!("frame_{index:05}.png")))?;
+                    }
+                    Ok(())
+                }
+                RecordFormat::Y4m => {
+                    //opening a pipe waits for a reader, so it is done here
+                    let mut out = BufWriter::new(File::create(&out_path)?);
+                    let mut size = None;
+
+                    for frame in received {
+                        let dimensions = frame.dimensions();
+                        match size {
+                            None => {
+                                write_y4m_header(&mut out, dimensions, fps)?;
+                                size = Some(dimensions);
+                            }
+                            Some(size) if size != dimensions => {
+                                bail!("Y4M recordings can't change resolution")
+                            }
+                            _ => {}
+                        }
+                        write_y4m_frame(&mut out, &frame)?;
+                    }
+                    out.flush()?;
+                    Ok(())
+                }
+            }
+        });
+
+        self.recording = Some(Recording {
+            destination,
+            frames,
+            writer,
+            interval: 1.0 / fps,
+            next_time: None,
+            pending: None,
+        });
+
+        Ok(())
+    }
+
+    ///Queue the last frame and let the writer finish in the background
+    fn stop(&mut self) {
+        if let Some(recording) = self.recording.take() {
+            if let Some(pending) = recording.pending {
+                //dropped if the queue is full or the writer has stopped
+                if let Ok(frame) = pending.finish() {
+                    let _ = recording.frames.try_send(frame);
+                }
+            }
+
+            drop(recording.frames);
+            self.finishing.push(recording.writer);
+        }
+    }
+
+    ///Remove the writers that are done, returning the first error
+    fn poll_finished(&mut self) -> anyhow::Result<()> {
+        let (finished, writing) = std::mem::take(&mut self.finishing)
+            .into_iter()
+            .partition::<Vec<_>, _>(JoinHandle::is_finished);
+        self.finishing = writing;
+
+        for writer in finished {
+            writer
+                .join()
+                .map_err(|_| anyhow!("Recording thread panicked"))??;
+        }
+        Ok(())
+    }
+
+    ///Start copying the texture if a frame is due at this graph time.
+    /// The copy is queued on the next due frame, so the gpu isn't waited on
+    pub fn send(&mut self, texture: &Texture2d, time: f32) -> anyhow::Result<()> {
+        if let Some(recording) = &mut self.recording {
+            let next_time = recording.next_time.unwrap_or(time);
+            if time < next_time {
+                return Ok(());
+            }
+            //don't try to catch up after a seek or stall
+            recording.next_time = Some((next_time + recording.interval).max(time));
+
+            let previous = recording.pending.replace(TextureReadback::start(texture));
+
+            if let Some(previous) = previous {
+                match recording.frames.try_send(previous.finish()?) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(_)) => bail!("Recorder is behind, dropped a frame"),
+                    Err(TrySendError::Disconnected(_)) => {
+                        //the writer stopped early, its error is reported once it is joined
+                        self.stop();
+                        return self.poll_finished();
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for RecordOutShader {
+    fn drop(&mut self) {
+        self.stop();
+        if let Err(err) = self.poll_finished() {
+            eprintln!("Error finishing recording: {err:?}");
+        }
+    }
+}
+
+fn write_y4m_header(
+    out: &mut impl Write,
+    (width, height): (u32, u32),
+    fps: f32,
+) -> std::io::Result<()> {
+    //fps as a fraction, keeping 3 decimals
+    let fps_num = (fps * 1000.0).round() as u32;
+    writeln!(
+        out,
+        "YUV4MPEG2 W{width} H{height} F{fps_num}:1000 Ip A1:1 C444"
+    )
+}
+
+///BT.601 limited range, one full plane each for Y, Cb and Cr
+fn write_y4m_frame(out: &mut impl Write, frame: &RgbaImage) -> std::io::Result<()> {
+    let pixels = frame.pixels().map(|pixel| {
+        let [r, g, b, _] = pixel.0.map(|c| c as f32 / 255.0);
+        [
+            16.0 + 65.481 * r + 128.553 * g + 24.966 * b,
+            128.0 - 37.797 * r - 74.203 * g + 112.0 * b,
+            128.0 + 112.0 * r - 93.786 * g - 18.214 * b,
+        ]
+    });
+
+    let mut planes = [vec![], vec![], vec![]];
+    for yuv in pixels {
+        for (plane, value) in planes.iter_mut().zip(yuv) {
+            plane.push(value.round().clamp(0.0, 255.0) as u8);
+        }
+    }
+
+    out.write_all(b"FRAME\n")?;
+    for plane in planes {
+        out.write_all(&plane)?;
+    }
+
+    Ok(())
+}
@@ -3,10 +3,25 @@ use glium::{Texture2d};
 #[cfg(target_os="windows")]
 use spout_rust::SpoutSender;
 
+#[cfg(target_os="linux")]
+use shm_frames::ShmWriter;
+
+#[cfg(target_os="linux")]
+use crate::textures::readback::TextureReadback;
+
 
 pub struct SpoutOutShader {
     #[cfg(target_os="windows")]
-    spout: SpoutSender
+    spout: SpoutSender,
+    ///Shared memory ring buffer that other processes can read, see the shm-frames crate.
+    /// Only made once the node name is known, so previews of the node don't publish anything
+    #[cfg(target_os="linux")]
+    shm: Option<ShmWriter>,
+    ///Copy of the last frame, published on the next send so the gpu isn't waited on
+    #[cfg(target_os="linux")]
+    pending: Option<TextureReadback>,
+    ///Name the output was last published under, with the error if that failed
+    name: Option<(String, Option<String>)>,
 }
 
 impl SpoutOutShader {
@@ -15,12 +30,68 @@ impl SpoutOutShader {
         let spout = SpoutSender::new("RustSpoutOut");
         Self {
             #[cfg(target_os="windows")]
-            spout
+            spout,
+            #[cfg(target_os="linux")]
+            shm: None,
+            #[cfg(target_os="linux")]
+            pending: None,
+            name: None,
+        }
+    }
+
+    ///Publish under a new name. Only tries again when the name changes
+    pub fn set_name(&mut self, name: &str) -> anyhow::Result<()> {
+        if let Some((last_name, error)) = &self.name {
+            if last_name == name {
+                return match error {
+                    Some(error) => Err(anyhow::anyhow!("{error}")),
+                    None => Ok(()),
+                };
+            }
         }
+
+        let result = self.publish(name);
+        self.name = Some((name.to_string(), result.as_ref().err().map(ToString::to_string)));
+        result
     }
 
-    pub fn send(&mut self, _texture: &Texture2d) {
+    #[cfg_attr(not(target_os="linux"), allow(unused_variables))]
+    fn publish(&mut self, name: &str) -> anyhow::Result<()> {
+        #[cfg(target_os="linux")]
+        {
+            //unpublish the old name first, in case it is reused
+            self.shm = None;
+            self.pending = None;
+            if !name.is_empty() {
+                self.shm = Some(ShmWriter::create(&shm_name(name))?);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg_attr(not(any(target_os="windows", target_os="linux")), allow(unused_variables))]
+    pub fn send(&mut self, texture: &Texture2d) {
         #[cfg(target_os="windows")]
         self.spout.send_texture(gl::TEXTURE_2D, texture.get_id(), texture.width(), texture.height());
+
+        #[cfg(target_os="linux")]
+        if let Some(shm) = &mut self.shm {
+            //publishes a frame late, by which time the copy has finished
+            if let Some(previous) = self.pending.replace(TextureReadback::start(texture)) {
+                let written = previous.finish().and_then(|image| {
+                    Ok(shm.write(image.width(), image.height(), image.as_raw())?)
+                });
+                if let Err(err) = written {
+                    eprintln!("Error writing shared memory output: {err}");
+                }
+            }
+        }
     }
-}
\ No newline at end of file
+}
+
+///Shared memory names are a single path component starting with a slash
+#[cfg(target_os="linux")]
+fn shm_name(name: &str) -> String {
+    format!("/{}", name.trim_start_matches('/').replace('/', "_"))
+}
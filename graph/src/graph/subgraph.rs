@@ -0,0 +1,308 @@
+use common::clock::FrameTime;
+use egui_node_graph::{InputId, NodeId};
+use epaint::Pos2;
+use glium::backend::Facade;
+use serde::{Deserialize, Serialize};
+use slotmap::{SecondaryMap, SparseSecondaryMap};
+
+use super::{
+    animator::Animator,
+    def::{Graph, NodeData, UniqueNodeName},
+    graph_change_listener::MultipleUpdatesListener,
+    graph_utils::ProcessedInputs,
+    node_types::NodeType,
+    GraphShaderProcessor,
+};
+use crate::{
+    common::{
+        connections::{ConnectionType, ConnectionValue, InputDef, NodeOutputs, OutputDef},
+        def::UiValue,
+    },
+    def::GetUiValue,
+    textures::TextureManager,
+};
+
+///A graph that is rendered inside of a single node.
+/// SubgraphInput and SubgraphOutput nodes become the ports of the outer node
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SubgraphDef {
+    pub graph: Graph,
+    pub node_names: SecondaryMap<NodeId, UniqueNodeName>,
+    pub node_positions: SecondaryMap<NodeId, Pos2>,
+    pub animator: Animator,
+
+    ///Parameters of inner nodes that are shown on the outer node
+    pub exposed: Vec<(NodeId, String)>,
+
+    ///Incremented whenever the inner graph is edited
+    #[serde(skip)]
+    pub version: u64,
+}
+
+impl SubgraphDef {
+    ///A subgraph that passes a texture straight through
+    pub fn new() -> Self {
+        let mut def = Self::default();
+
+        let input = def.add_node(
+            NodeType::SubgraphInput {
+                ty: ConnectionType::Texture2D,
+            },
+            Pos2::new(0.0, 0.0),
+        );
+        let output = def.add_node(
+            NodeType::SubgraphOutput {
+                ty: ConnectionType::Texture2D,
+            },
+            Pos2::new(300.0, 0.0),
+        );
+
+        let output_id = def.graph[input].output_ids().next().unwrap();
+        let input_id = def.graph[output].input_ids().next().unwrap();
+        def.graph.add_connection(output_id, input_id);
+
+        def
+    }
+
+    fn add_node(&mut self, template: NodeType, pos: Pos2) -> NodeId {
+        let num = self
+            .graph
+            .nodes
+            .values()
+            .filter(|node| node.user_data.template.same_template(&template))
+            .count();
+        let name = UniqueNodeName::new(template.get_name().to_string(), num);
+
+        let node_id = self.graph.add_node(
+            name.to_string(),
+            NodeData::new(template.clone()),
+            |graph, node_id| template.build_node(graph, node_id),
+        );
+
+        self.node_names.insert(node_id, name);
+        self.node_positions.insert(node_id, pos);
+
+        node_id
+    }
+
+    ///The outer nodes would have the same inputs and outputs. The inner graphs may differ
+    pub fn same_ports(&self, other: &Self) -> bool {
+        self.input_types() == other.input_types() && self.output_types() == other.output_types()
+    }
+
+    ///Nodes that receive the inputs of the outer node
+    pub fn input_ports(&self) -> impl Iterator<Item = (NodeId, &str, ConnectionType)> {
+        self.graph
+            .nodes
+            .iter()
+            .filter_map(|(node_id, node)| match node.user_data.template {
+                NodeType::SubgraphInput { ty } => Some((node_id, node.label.as_str(), ty)),
+                _ => None,
+            })
+    }
+
+    ///Nodes whose input is sent out of the outer node
+    pub fn output_ports(&self) -> impl Iterator<Item = (NodeId, &str, ConnectionType)> {
+        self.graph
+            .nodes
+            .iter()
+            .filter_map(|(node_id, node)| match node.user_data.template {
+                NodeType::SubgraphOutput { ty } => Some((node_id, node.label.as_str(), ty)),
+                _ => None,
+            })
+    }
+
+    ///Exposed parameters that still exist, named by node and parameter
+    fn exposed_inputs(&self) -> impl Iterator<Item = (String, InputId)> + '_ {
+        self.exposed.iter().filter_map(|(node_id, param)| {
+            let node = self.graph.nodes.get(*node_id)?;
+            let input_id = node.get_input(param).ok()?;
+
+            Some((format!("{}.{param}", node.label), input_id))
+        })
+    }
+
+    pub fn input_types(&self) -> Vec<InputDef> {
+        let ports = self.input_ports().map(|(_, name, ty)| InputDef {
+            name: name.to_string(),
+            ty,
+            value: ty.default_value(),
+        });
+
+        let exposed = self.exposed_inputs().map(|(name, input_id)| {
+            let value = self.graph[input_id].value.clone();
+            InputDef::from((name.as_str(), value))
+        });
+
+        ports.chain(exposed).collect()
+    }
+
+    ///Each output port becomes an output named after it
+    pub fn output_types(&self) -> Vec<OutputDef> {
+        self.output_ports()
+            .map(|(_, name, ty)| (name, ty).into())
+            .collect()
+    }
+
+    ///Copy the values of the outer node into the exposed parameters.
+    /// Returns true if any were changed
+    pub fn set_exposed_values(&mut self, values: &[(String, UiValue)]) -> bool {
+        let targets: Vec<_> = self
+            .exposed_inputs()
+            .filter_map(|(name, input_id)| {
+                let (_, value) = values.iter().find(|(outer_name, _)| *outer_name == name)?;
+                Some((input_id, value))
+            })
+            .collect();
+
+        let mut changed = false;
+        for (input_id, value) in targets {
+            let inner = &mut self.graph[input_id].value;
+            if *inner != *value {
+                *inner = value.clone();
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+///Renders the inner graph of a subgraph node
+#[derive(Debug, Default)]
+pub struct SubgraphProcessor {
+    processor: GraphShaderProcessor,
+    version: u64,
+    ///Time of the last update, animations only move when it changes
+    last_time: Option<f32>,
+}
+
+impl SubgraphProcessor {
+    pub fn new(def: &mut SubgraphDef, facade: &impl Facade) -> anyhow::Result<Self> {
+        Ok(Self {
+            processor: GraphShaderProcessor::new_from_graph(&mut def.graph, facade)?,
+            version: def.version,
+            last_time: None,
+        })
+    }
+
+    ///If the inner graph changes output every frame
+    pub fn is_time_dependent(&self) -> bool {
+        self.processor.is_time_dependent()
+    }
+
+    ///Rebuild if the inner graph was edited and update each inner node.
+    /// values are the parameters of the outer node.
+    /// Returns true if the output may have changed
+    pub fn update(
+        &mut self,
+        def: &mut SubgraphDef,
+        values: &[(String, UiValue)],
+        time: &FrameTime,
+        facade: &impl Facade,
+    ) -> anyhow::Result<bool> {
+        let rebuilt = if self.version != def.version {
+            *self = Self::new(def, facade)?;
+            true
+        } else {
+            false
+        };
+
+        let exposed_changed = def.set_exposed_values(values);
+
+        let errors = self.processor.update(&mut def.graph, time, facade);
+        def.animator.update(&mut def.graph, time);
+
+        let time_changed = self.last_time.replace(time.time) != Some(time.time);
+        let animated = time_changed && !def.animator.animations.is_empty();
+
+        match errors.into_iter().next() {
+            Some((node_id, err)) => Err(err.context(format!("In {}", def.graph[node_id].label))),
+            None => Ok(rebuilt || exposed_changed || self.processor.is_dirty() || animated),
+        }
+    }
+
+    pub fn resize(
+        &mut self,
+        def: &SubgraphDef,
+        facade: &impl Facade,
+        texture_manager: &mut TextureManager,
+        res: (u32, u32),
+    ) {
+        self.processor
+            .resize(&def.graph, facade, texture_manager, res);
+    }
+
+    ///Send the inputs of the outer node into the inner graph and render it
+    pub fn render<C, V: GetUiValue>(
+        &mut self,
+        def: &SubgraphDef,
+        facade: &impl Facade,
+        texture_manager: &mut TextureManager,
+        time: &FrameTime,
+        inputs: &ProcessedInputs<'_, C, V, ConnectionValue>,
+    ) -> anyhow::Result<Option<NodeOutputs>> {
+        let outer_value = |name: &str| {
+            inputs
+                .iter()
+                .find(|(input_name, ..)| *input_name == name)
+                .and_then(|(_, input, connected)| {
+                    connected
+                        .clone()
+                        .or_else(|| ConnectionValue::from_ui_value(input.value.ui_value()))
+                })
+        };
+
+        let mut external_outputs = SecondaryMap::new();
+        for (node_id, name, _) in def.input_ports() {
+            if let Some(value) = outer_value(name) {
+                external_outputs.insert(node_id, value);
+            }
+        }
+
+        //unconnected parameters are copied in update
+        let mut input_overrides = SecondaryMap::new();
+        for (name, input_id) in def.exposed_inputs() {
+            let connected = inputs
+                .iter()
+                .find(|(input_name, ..)| *input_name == name)
+                .and_then(|(.., connected)| connected.clone());
+
+            if let Some(value) = connected {
+                input_overrides.insert(input_id, value);
+            }
+        }
+
+        self.processor.set_external(external_outputs, input_overrides);
+
+        let response =
+            self.processor
+                .render_shaders(&def.graph, facade, texture_manager, time, |_, _| {});
+
+        //in the order of the outer sockets, including ports without a value
+        let named: Vec<_> = def
+            .output_ports()
+            .map(|(node_id, name, _)| (name.to_string(), self.processor.output(node_id).cloned()))
+            .collect();
+
+        //the first port is the main output, used for previews
+        let main = named.first().and_then(|(_, main)| main.clone());
+        let output = main.map(|main| NodeOutputs { main, named });
+
+        match (output, first_error(&def.graph, response.errors)) {
+            (Some(output), _) => Ok(Some(output)),
+            (None, Some(err)) => Err(err),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+fn first_error(
+    graph: &Graph,
+    errors: SparseSecondaryMap<NodeId, super::def::NodeError>,
+) -> Option<anyhow::Error> {
+    errors
+        .into_iter()
+        .next()
+        .map(|(node_id, err)| anyhow::anyhow!("In {}: {}", graph[node_id].label, err.text))
+}
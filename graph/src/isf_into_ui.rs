@@ -9,8 +9,9 @@ impl From<&InputType> for ConnectionType {
     fn from(ty: &InputType) -> Self {
         match ty {
             InputType::Image => ConnectionType::Texture2D,
-            // InputType::Float(_) => Ok(NodeConnectionTypes::None),
-            // InputType::Point2d(_) => NodeConnectionTypes::Texture2D,
+            InputType::Float(_) => ConnectionType::Float,
+            InputType::Point2d(_) => ConnectionType::Vec2,
+            InputType::Color(_) => ConnectionType::Color,
             _ => ConnectionType::None,
         }
     }
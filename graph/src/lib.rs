@@ -8,10 +8,13 @@ pub use graph::def::*;
 pub use graph::graph_change_listener::*;
 pub use graph::node_shader::*;
 pub use graph::node_types::*;
-pub use textures::TextureManager;
+pub use textures::audio::AudioTextures;
+pub use textures::readback::{read_texture, TextureReadback};
+pub use textures::{GetOutputSettings, OutputRes, OutputSettings, TextureManager};
 
 pub use graph::animator::Animator;
 pub use graph::GraphShaderProcessor;
+pub use graph::subgraph::SubgraphDef;
 
 // #[macro_use]
 // extern crate partial_application;
@@ -0,0 +1,142 @@
+use std::{borrow::Cow, collections::HashMap, rc::Rc};
+
+use audio::{
+    analysis::{DEFAULT_FFT_BINS, DEFAULT_WAVEFORM_SAMPLES},
+    AudioImage, AudioSource, FftAnalyzer,
+};
+use common::clock::FrameTime;
+use glium::{
+    backend::Facade,
+    texture::{ClientFormat, MipmapsOption, RawImage2d, UncompressedFloatFormat},
+    Rect, Texture2d,
+};
+use shaders::isf::shader::{IsfAudioInput, IsfAudioKind};
+
+///Textures for the ISF audio inputs, filled from one source shared by every node
+#[derive(Default)]
+pub struct AudioTextures {
+    source: Option<(String, Box<dyn AudioSource>)>,
+    analyzer: FftAnalyzer,
+    ///By kind and width, with the frame they were last filled for
+    textures: HashMap<(IsfAudioKind, u32), (Rc<Texture2d>, FrameTime)>,
+}
+
+impl std::fmt::Debug for AudioTextures {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AudioTextures")
+            .field("source", &self.source_name())
+            .field("textures", &self.textures.len())
+            .finish()
+    }
+}
+
+impl AudioTextures {
+    ///name is shown in the ui
+    pub fn set_source(&mut self, name: impl Into<String>, source: Box<dyn AudioSource>) {
+        self.source = Some((name.into(), source));
+        self.textures.clear();
+    }
+
+    ///Audio inputs are left unbound
+    pub fn clear_source(&mut self) {
+        self.source = None;
+        self.textures.clear();
+    }
+
+    pub fn source_name(&self) -> Option<&str> {
+        self.source.as_ref().map(|(name, _)| name.as_str())
+    }
+
+    ///The image of an ISF audio input at this frame, analysed once no matter how many nodes use it.
+    /// None without a source
+    pub fn texture(
+        &mut self,
+        facade: &impl Facade,
+        time: &FrameTime,
+        input: &IsfAudioInput,
+    ) -> Option<Rc<Texture2d>> {
+        let (_, source) = self.source.as_mut()?;
+
+        let width = match input.kind {
+            IsfAudioKind::Waveform => input.max.unwrap_or(DEFAULT_WAVEFORM_SAMPLES),
+            IsfAudioKind::Fft => input.max.unwrap_or(DEFAULT_FFT_BINS),
+        }
+        .max(1);
+        let key = (input.kind, width);
+
+        if let Some((texture, filled)) = self.textures.get(&key) {
+            if filled == time {
+                return Some(texture.clone());
+            }
+        }
+
+        let image = match input.kind {
+            IsfAudioKind::Waveform => {
+                AudioImage::waveform(&source.window(time.time, width as usize), width)
+            }
+            IsfAudioKind::Fft => {
+                let waveform = source.window(time.time, FftAnalyzer::window_len(width));
+                self.analyzer.analyze(&waveform, width)
+            }
+        };
+
+        if image.height == 0 {
+            return None;
+        }
+
+        let texture = match self.textures.get(&key) {
+            //the channel count can change with the source
+            Some((texture, _)) if texture.dimensions() == (image.width, image.height) => {
+                texture.write(
+                    Rect {
+                        left: 0,
+                        bottom: 0,
+                        width: image.width,
+                        height: image.height,
+                    },
+                    raw_image(&image),
+                );
+                texture.clone()
+            }
+            _ => match new_audio_texture(facade, &image) {
+                Ok(texture) => Rc::new(texture),
+                Err(err) => {
+                    eprintln!("Failed to make audio texture: {err:?}");
+                    return None;
+                }
+            },
+        };
+
+        self.textures.insert(key, (texture.clone(), *time));
+
+        Some(texture)
+    }
+}
+
+///Grey pixels, so any channel can be sampled
+fn raw_image(image: &AudioImage) -> RawImage2d<'static, f32> {
+    let data = image
+        .values
+        .iter()
+        .flat_map(|value| [*value, *value, *value, 1.0])
+        .collect::<Vec<_>>();
+
+    RawImage2d {
+        data: Cow::Owned(data),
+        width: image.width,
+        height: image.height,
+        format: ClientFormat::F32F32F32F32,
+    }
+}
+
+fn new_audio_texture(
+    facade: &impl Facade,
+    image: &AudioImage,
+) -> Result<Texture2d, glium::texture::TextureCreationError> {
+    Texture2d::with_format(
+        facade,
+        raw_image(image),
+        UncompressedFloatFormat::F32F32F32F32,
+        MipmapsOption::NoMipmap,
+    )
+}
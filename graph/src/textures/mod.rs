@@ -1,26 +1,72 @@
-use std::rc::Rc;
+use std::{collections::HashMap, rc::Rc};
 
 use glium::{
     backend::Facade,
     texture::{DepthTexture2d},
     Texture2d,
 };
+use serde::{Deserialize, Serialize};
 
 use common::texture::*;
 
+pub mod audio;
+pub mod readback;
+
+///Size of the texture a node renders into
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum OutputRes {
+    ///Multiple of the project resolution
+    Fraction(f32),
+    Absolute(u32, u32),
+}
+
+impl Default for OutputRes {
+    fn default() -> Self {
+        OutputRes::Fraction(1.0)
+    }
+}
+
+impl OutputRes {
+    pub fn resolve(&self, (width, height): (u32, u32)) -> (u32, u32) {
+        match *self {
+            OutputRes::Fraction(fraction) => (
+                ((width as f32 * fraction).round() as u32).max(1),
+                ((height as f32 * fraction).round() as u32).max(1),
+            ),
+            OutputRes::Absolute(width, height) => (width.max(1), height.max(1)),
+        }
+    }
+}
+
+///How a node allocates its output texture
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct OutputSettings {
+    pub res: OutputRes,
+    pub format: TextureFormat,
+}
+
+pub trait GetOutputSettings {
+    fn output_settings(&self) -> &OutputSettings;
+    fn output_settings_mut(&mut self) -> &mut OutputSettings;
+}
+
 #[derive(Debug)]
 pub struct TextureManager {
-    pub color_textures: Vec<Rc<Texture2d>>,
-    pub depth_textures: Vec<Rc<DepthTexture2d>>,
+    pub color_textures: HashMap<((u32, u32), TextureFormat), Vec<Rc<Texture2d>>>,
+    pub depth_textures: HashMap<(u32, u32), Vec<Rc<DepthTexture2d>>>,
+    ///Project resolution, which node resolutions are relative to
     pub res: (u32, u32),
+    ///Bound to the ISF audio inputs
+    pub audio: audio::AudioTextures,
 }
 
 impl Default for TextureManager {
     fn default() -> Self {
         Self {
-            color_textures: Vec::new(),
-            depth_textures: Vec::new(),
+            color_textures: HashMap::new(),
+            depth_textures: HashMap::new(),
             res: DEFAULT_RES,
+            audio: Default::default(),
         }
     }
 }
@@ -37,15 +83,31 @@ fn get_unused_or_push<T>(vec: &mut Vec<Rc<T>>, f: impl Fn() -> T) -> Rc<T> {
 
 //Handles shared references of textures and will allocate new textures as needed
 impl TextureManager {
+    ///A texture at the project resolution
     pub fn get_color(&mut self, facade: &impl Facade) -> Rc<Texture2d> {
-        get_unused_or_push(&mut self.color_textures, || {
-            new_texture_2d(facade, self.res).unwrap()
+        self.get_color_with(facade, &OutputSettings::default())
+    }
+
+    ///Textures are pooled by size and format
+    pub fn get_color_with(
+        &mut self,
+        facade: &impl Facade,
+        settings: &OutputSettings,
+    ) -> Rc<Texture2d> {
+        let res = settings.res.resolve(self.res);
+        let pool = self
+            .color_textures
+            .entry((res, settings.format))
+            .or_default();
+
+        get_unused_or_push(pool, || {
+            new_texture_2d_with_format(facade, res, settings.format).unwrap()
         })
     }
 
-    pub fn get_depth(&mut self, facade: &impl Facade) -> Rc<DepthTexture2d> {
-        get_unused_or_push(&mut self.depth_textures, || {
-            new_depth_texture_2d(facade, self.res).unwrap()
+    pub fn get_depth(&mut self, facade: &impl Facade, res: (u32, u32)) -> Rc<DepthTexture2d> {
+        get_unused_or_push(self.depth_textures.entry(res).or_default(), || {
+            new_depth_texture_2d(facade, res).unwrap()
         })
     }
 
@@ -54,12 +116,13 @@ impl TextureManager {
         self.depth_textures.clear();
     }
 
-    // fn set_res(&mut self, facade: &impl Facade, res: (u32, u32)){
-    //     self.res = res;
-    //     for texture in self.color_textures.iter_mut() {
-    //         *texture = Rc::new(new_texture_depth_2d(facade, self.res).unwrap());
-    //     }
-    // }
+    ///Change the project resolution, dropping the pooled textures
+    pub fn set_res(&mut self, res: (u32, u32)) {
+        if res != self.res {
+            self.res = res;
+            self.clear();
+        }
+    }
 
     // fn get_or_set(&mut self, facade: &impl Facade, index: usize) -> &Texture2d {
     //     if self.textures.get(index).is_none() {
@@ -0,0 +1,42 @@
+use glium::{
+    texture::{pixel_buffer::PixelBuffer, RawImage2d},
+    Texture2d,
+};
+use image::RgbaImage;
+
+///Copy a texture into an image, blocking until the gpu has rendered it
+pub fn read_texture(texture: &Texture2d) -> RgbaImage {
+    let raw: RawImage2d<u8> = texture.read();
+    into_image(raw)
+}
+
+///Readback that copies into a pixel buffer on the gpu, so the cpu doesn't wait for rendering.
+/// Keep it until a later frame before calling [TextureReadback::finish], or it stalls like [read_texture]
+pub struct TextureReadback {
+    buffer: PixelBuffer<(u8, u8, u8, u8)>,
+}
+
+impl TextureReadback {
+    pub fn start(texture: &Texture2d) -> Self {
+        Self {
+            buffer: texture.read_to_pixel_buffer(),
+        }
+    }
+
+    ///Map the pixel buffer into an image
+    pub fn finish(self) -> anyhow::Result<RgbaImage> {
+        let raw: RawImage2d<u8> = self
+            .buffer
+            .read_as_texture_2d()
+            .map_err(|err| anyhow::anyhow!("Could not read pixel buffer {err:?}"))?;
+        Ok(into_image(raw))
+    }
+}
+
+fn into_image(raw: RawImage2d<u8>) -> RgbaImage {
+    let mut image = RgbaImage::from_raw(raw.width, raw.height, raw.data.into_owned())
+        .expect("Texture data should match its size");
+    //gl rows start at the bottom
+    image::imageops::flip_vertical_in_place(&mut image);
+    image
+}
@@ -0,0 +1,72 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{read_from_json_file, write_to_json_file};
+
+///User settings shared by every project
+#[derive(Default, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Config {
+    ///ISF library folders searched along with the default ones
+    #[serde(default)]
+    pub isf_dirs: Vec<PathBuf>,
+}
+
+impl Config {
+    ///render-graph/config.json in the platform config dir
+    pub fn default_path() -> PathBuf {
+        config_dir().join("render-graph").join("config.json")
+    }
+
+    pub fn load_from_default_path() -> Self {
+        let file = Self::default_path();
+        match read_from_json_file::<Self>(&file) {
+            Ok(config) => config,
+            Err(err) => {
+                //a missing file just means nothing was configured yet
+                if file.exists() {
+                    eprintln!("Failed to read config {file:?}\nERR({err:?}). Using default config");
+                }
+                Self::default()
+            }
+        }
+    }
+
+    pub fn write_to_default_path(&self) -> anyhow::Result<()> {
+        let file = Self::default_path();
+        if let Some(dir) = file.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        write_to_json_file(&file, self)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn config_dir() -> PathBuf {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home_dir().join(".config"))
+}
+
+#[cfg(target_os = "macos")]
+fn config_dir() -> PathBuf {
+    home_dir().join("Library/Application Support")
+}
+
+#[cfg(target_os = "windows")]
+fn config_dir() -> PathBuf {
+    env::var_os("APPDATA")
+        .map(PathBuf::from)
+        .unwrap_or_else(home_dir)
+}
+
+fn home_dir() -> PathBuf {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(".").to_path_buf())
+}
@@ -4,6 +4,7 @@ use graph::{
     UniqueNodeName,
 };
 
+pub mod config;
 pub mod ui_state;
 
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
@@ -101,3 +102,8 @@ pub fn read_from_json_file<T: DeserializeOwned>(path: &Path) -> anyhow::Result<T
     let file = File::open(path)?;
     Ok(serde_json::from_reader(file)?)
 }
+
+///Convert between types that share the same serialized form, like graph and editor nodes
+pub fn convert<A: Serialize, B: DeserializeOwned>(value: &A) -> anyhow::Result<B> {
+    Ok(serde_json::from_value(serde_json::to_value(value)?)?)
+}
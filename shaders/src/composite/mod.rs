@@ -0,0 +1,212 @@
+use glium::{
+    backend::Facade,
+    uniforms::{UniformValue, Uniforms},
+    DrawError, DrawParameters, Surface,
+};
+
+use crate::{
+    fullscreen_shader::FullscreenFrag,
+    util::{GlProgramCreationError, MultiUniforms},
+};
+
+///How a layer is combined with the layers below it
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BlendMode {
+    Normal,
+    Add,
+    Subtract,
+    Multiply,
+    Screen,
+    Overlay,
+    Difference,
+    Lighten,
+    Darken,
+}
+
+impl BlendMode {
+    ///In the order of the blend uniform values
+    pub const ALL: [BlendMode; 9] = [
+        BlendMode::Normal,
+        BlendMode::Add,
+        BlendMode::Subtract,
+        BlendMode::Multiply,
+        BlendMode::Screen,
+        BlendMode::Overlay,
+        BlendMode::Difference,
+        BlendMode::Lighten,
+        BlendMode::Darken,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlendMode::Normal => "normal",
+            BlendMode::Add => "add",
+            BlendMode::Subtract => "subtract",
+            BlendMode::Multiply => "multiply",
+            BlendMode::Screen => "screen",
+            BlendMode::Overlay => "overlay",
+            BlendMode::Difference => "difference",
+            BlendMode::Lighten => "lighten",
+            BlendMode::Darken => "darken",
+        }
+    }
+}
+
+pub fn layer_name(index: usize) -> String {
+    format!("layer_{index}")
+}
+
+pub fn opacity_name(index: usize) -> String {
+    format!("opacity_{index}")
+}
+
+pub fn blend_name(index: usize) -> String {
+    format!("blend_{index}")
+}
+
+pub fn mask_name(index: usize) -> String {
+    format!("mask_{index}")
+}
+
+///Blends textures on top of each other in a single pass.
+/// Layer 0 is at the bottom, each layer has an opacity, blend mode and optional mask
+#[derive(Debug)]
+pub struct CompositeRenderer {
+    frag: FullscreenFrag,
+    layers: usize,
+}
+
+impl CompositeRenderer {
+    pub fn new(facade: &impl Facade, layers: usize) -> Result<Self, GlProgramCreationError> {
+        //the shader does the blending itself
+        let frag = FullscreenFrag::new_with_params(
+            facade,
+            &build_source(layers),
+            DrawParameters::default(),
+        )?;
+
+        Ok(Self { frag, layers })
+    }
+
+    pub fn layers(&self) -> usize {
+        self.layers
+    }
+
+    ///connected holds the names of the texture uniforms that are set,
+    /// so unconnected layers and masks can be skipped
+    pub fn draw(
+        &self,
+        surface: &mut impl Surface,
+        uniforms: &impl Uniforms,
+        connected: &[String],
+    ) -> Result<(), DrawError> {
+        let bits = |name: fn(usize) -> String| {
+            (0..self.layers)
+                .filter(|i| connected.contains(&name(*i)))
+                .fold(0, |bits, i| bits | 1 << i)
+        };
+
+        let uniforms = MultiUniforms {
+            uniforms: vec![
+                (
+                    "connected_layers",
+                    UniformValue::SignedInt(bits(layer_name)),
+                ),
+                ("connected_masks", UniformValue::SignedInt(bits(mask_name))),
+            ],
+            next: uniforms,
+        };
+
+        self.frag.draw(surface, &uniforms)
+    }
+}
+
+fn build_source(layers: usize) -> String {
+    let blend_cases: String = BlendMode::ALL
+        .iter()
+        .enumerate()
+        .map(|(i, mode)| {
+            let expr = match mode {
+                BlendMode::Normal => "s",
+                BlendMode::Add => "min(b + s, 1.0)",
+                BlendMode::Subtract => "max(b - s, 0.0)",
+                BlendMode::Multiply => "b * s",
+                BlendMode::Screen => "b + s - b * s",
+                BlendMode::Overlay => {
+                    "mix(2.0 * b * s, 1.0 - 2.0 * (1.0 - b) * (1.0 - s), step(0.5, b))"
+                }
+                BlendMode::Difference => "abs(b - s)",
+                BlendMode::Lighten => "max(b, s)",
+                BlendMode::Darken => "min(b, s)",
+            };
+            format!("        case {i}: return {expr};\n")
+        })
+        .collect();
+
+    let uniforms: String = (0..layers)
+        .map(|i| {
+            format!(
+                "uniform sampler2D {};\nuniform float {};\nuniform int {};\nuniform sampler2D {};\n",
+                layer_name(i),
+                opacity_name(i),
+                blend_name(i),
+                mask_name(i)
+            )
+        })
+        .collect();
+
+    let apply_layers: String = (0..layers)
+        .map(|i| {
+            format!(
+                "    color = apply_layer(color, uv, {i}, {}, {}, {}, {});\n",
+                layer_name(i),
+                opacity_name(i),
+                blend_name(i),
+                mask_name(i)
+            )
+        })
+        .collect();
+
+    format!(
+        "
+#version 140
+uniform vec2 res;
+uniform int connected_layers;
+uniform int connected_masks;
+{uniforms}
+out vec4 out_color;
+
+vec3 blend(int mode, vec3 b, vec3 s) {{
+    switch (mode) {{
+{blend_cases}        default: return s;
+    }}
+}}
+
+vec4 apply_layer(vec4 color, vec2 uv, int index, sampler2D layer, float opacity, int mode, sampler2D mask) {{
+    if ((connected_layers & (1 << index)) == 0) {{
+        return color;
+    }}
+
+    vec4 src = texture(layer, uv);
+    float alpha = src.a * clamp(opacity, 0.0, 1.0);
+    if ((connected_masks & (1 << index)) != 0) {{
+        vec4 mask_pixel = texture(mask, uv);
+        alpha *= dot(mask_pixel.rgb, vec3(0.299, 0.587, 0.114)) * mask_pixel.a;
+    }}
+
+    //blend only where there is something below
+    vec3 blended = mix(src.rgb, blend(mode, color.rgb, src.rgb), color.a);
+    float out_alpha = alpha + color.a * (1.0 - alpha);
+    vec3 rgb = (blended * alpha + color.rgb * color.a * (1.0 - alpha)) / max(out_alpha, 0.0001);
+
+    return vec4(rgb, out_alpha);
+}}
+
+void main() {{
+    vec2 uv = gl_FragCoord.xy / res;
+    vec4 color = vec4(0.0);
+{apply_layers}    out_color = color;
+}}
+"
+    )
+}
@@ -145,12 +145,13 @@ pub struct GlExpressionUpdater {
 }
 
 impl GlExpressionUpdater {
+    ///Returns true if the shader was rebuilt
     pub fn update(
         &mut self,
         facade: &impl Facade,
         renderer: &mut GlExpressionRenderer,
         new_frag: String,
-    ) -> Result<(), GlProgramCreationError> {
+    ) -> Result<bool, GlProgramCreationError> {
         let should_update_frag = match &self.frag_source {
             Some(shader) => shader != &new_frag,
             None => true,
@@ -161,6 +162,6 @@ impl GlExpressionUpdater {
             self.frag_source = Some(new_frag);
         }
 
-        Ok(())
+        Ok(should_update_frag)
     }
 }
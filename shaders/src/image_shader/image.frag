@@ -0,0 +1,33 @@
+#version 140
+
+uniform vec2 res;
+uniform sampler2D image;
+uniform vec2 image_res;
+uniform int fit;
+
+out vec4 out_color;
+
+const int FIT = 0;
+const int FILL = 1;
+
+void main() {
+    vec2 uv = gl_FragCoord.xy / res;
+
+    //output aspect relative to the image aspect
+    float ratio = (res.x / res.y) / (image_res.x / image_res.y);
+
+    vec2 scale = vec2(1.0);
+    if (fit == FIT) {
+        scale = ratio > 1.0 ? vec2(ratio, 1.0) : vec2(1.0, 1.0 / ratio);
+    } else if (fit == FILL) {
+        scale = ratio > 1.0 ? vec2(1.0, 1.0 / ratio) : vec2(ratio, 1.0);
+    }
+
+    vec2 image_uv = (uv - 0.5) * scale + 0.5;
+
+    if (any(lessThan(image_uv, vec2(0.0))) || any(greaterThan(image_uv, vec2(1.0)))) {
+        out_color = vec4(0.0);
+    } else {
+        out_color = texture(image, image_uv);
+    }
+}
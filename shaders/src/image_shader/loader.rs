@@ -0,0 +1,100 @@
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use glium::backend::Facade;
+
+use super::{
+    renderer::{new_image_texture, ImageRenderer},
+    sequence::{list_frames, ImageSequence},
+};
+
+pub struct ImageLoader {
+    cur_file: Option<PathBuf>,
+    modified: SystemTime,
+}
+
+impl ImageLoader {
+    pub fn new() -> Self {
+        Self {
+            cur_file: None,
+            modified: SystemTime::UNIX_EPOCH,
+        }
+    }
+
+    ///Returns true if a new image was loaded
+    pub fn load_if_changed(
+        &mut self,
+        facade: &impl Facade,
+        path: &Path,
+        renderer: &mut ImageRenderer,
+    ) -> Result<bool, anyhow::Error> {
+        let last_modified = path.metadata()?.modified()?;
+
+        let do_load = match &self.cur_file {
+            None => true,
+            Some(cur_file) => cur_file != path || self.modified < last_modified,
+        };
+
+        if do_load {
+            println!("Updating image from {path:?}");
+
+            //remember the attempt so a broken file isn't decoded every frame
+            self.cur_file = Some(path.to_path_buf());
+            self.modified = last_modified;
+
+            let image = image::open(path)?;
+            renderer.set_texture(new_image_texture(facade, image)?);
+        }
+
+        Ok(do_load)
+    }
+}
+
+///Lists the frames of an image sequence, again when files are added or removed
+pub struct SequenceLoader {
+    cur_folder: Option<PathBuf>,
+    modified: SystemTime,
+}
+
+impl SequenceLoader {
+    pub fn new() -> Self {
+        Self {
+            cur_folder: None,
+            modified: SystemTime::UNIX_EPOCH,
+        }
+    }
+
+    ///path can be the folder or any frame inside of it.
+    /// Returns true if the frames were listed again
+    pub fn load_if_changed(
+        &mut self,
+        path: &Path,
+        sequence: &mut ImageSequence,
+    ) -> Result<bool, anyhow::Error> {
+        let folder = if path.is_dir() {
+            path
+        } else {
+            path.parent()
+                .ok_or_else(|| anyhow::anyhow!("No folder for {path:?}"))?
+        };
+        let last_modified = folder.metadata()?.modified()?;
+
+        let do_load = match &self.cur_folder {
+            None => true,
+            Some(cur_folder) => cur_folder != folder || self.modified < last_modified,
+        };
+
+        if do_load {
+            println!("Updating image sequence from {folder:?}");
+
+            self.cur_folder = Some(folder.to_path_buf());
+            self.modified = last_modified;
+
+            sequence.set_frames(list_frames(folder)?);
+        }
+
+        Ok(do_load)
+    }
+}
@@ -0,0 +1,3 @@
+pub mod loader;
+pub mod renderer;
+pub mod sequence;
@@ -0,0 +1,111 @@
+use glium::{
+    backend::Facade,
+    texture::{MipmapsOption, RawImage2d, UncompressedFloatFormat},
+    uniforms::{UniformValue, Uniforms},
+    DrawError, DrawParameters, Surface, Texture2d,
+};
+use image::DynamicImage;
+
+use crate::{
+    fullscreen_shader::FullscreenFrag,
+    util::{GlProgramCreationError, MultiUniforms},
+};
+
+///How the image is scaled to the output resolution
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ImageFit {
+    ///Show the whole image, leaving transparent borders
+    Fit,
+    ///Cover the whole output, cropping the image
+    Fill,
+    ///Match the output, ignoring the aspect ratio
+    Stretch,
+}
+
+impl ImageFit {
+    ///In the order of the fit uniform values
+    pub const ALL: [ImageFit; 3] = [ImageFit::Fit, ImageFit::Fill, ImageFit::Stretch];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ImageFit::Fit => "fit",
+            ImageFit::Fill => "fill",
+            ImageFit::Stretch => "stretch",
+        }
+    }
+}
+
+///Upload an image, keeping float precision for hdr formats like exr
+pub fn new_image_texture(facade: &impl Facade, image: DynamicImage) -> anyhow::Result<Texture2d> {
+    let dimensions = (image.width(), image.height());
+
+    let texture = match image {
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+            let data = image.into_rgba32f().into_raw();
+            Texture2d::with_format(
+                facade,
+                RawImage2d::from_raw_rgba_reversed(&data, dimensions),
+                UncompressedFloatFormat::F32F32F32F32,
+                MipmapsOption::NoMipmap,
+            )?
+        }
+        image => {
+            let data = image.into_rgba8().into_raw();
+            Texture2d::with_format(
+                facade,
+                RawImage2d::from_raw_rgba_reversed(&data, dimensions),
+                UncompressedFloatFormat::U8U8U8U8,
+                MipmapsOption::NoMipmap,
+            )?
+        }
+    };
+
+    Ok(texture)
+}
+
+///Draws an image file scaled to the output
+pub struct ImageRenderer {
+    frag: FullscreenFrag,
+    texture: Option<Texture2d>,
+}
+
+impl ImageRenderer {
+    pub fn new(facade: &impl Facade) -> Result<Self, GlProgramCreationError> {
+        Ok(Self {
+            frag: FullscreenFrag::new_with_params(
+                facade,
+                include_str!("image.frag"),
+                DrawParameters::default(),
+            )?,
+            texture: None,
+        })
+    }
+
+    pub fn set_texture(&mut self, texture: Texture2d) {
+        self.texture = Some(texture);
+    }
+
+    ///Draws nothing until an image is loaded
+    pub fn draw(
+        &self,
+        surface: &mut impl Surface,
+        uniforms: &impl Uniforms,
+    ) -> Result<(), DrawError> {
+        if let Some(texture) = &self.texture {
+            let (width, height) = texture.dimensions();
+            let image_res = [width as f32, height as f32];
+
+            let uniforms = MultiUniforms {
+                uniforms: vec![
+                    ("image", UniformValue::Texture2d(texture, None)),
+                    ("image_res", UniformValue::Vec2(image_res)),
+                ],
+                next: uniforms,
+            };
+
+            self.frag.draw(surface, &uniforms)?;
+        }
+
+        Ok(())
+    }
+}
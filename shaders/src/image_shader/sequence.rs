@@ -0,0 +1,224 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver, Sender},
+    thread,
+};
+
+use glium::{backend::Facade, uniforms::Uniforms, Surface};
+use image::DynamicImage;
+
+use super::renderer::{new_image_texture, ImageRenderer};
+use crate::util::GlProgramCreationError;
+
+///Frames decoded ahead of the one being shown
+const PRELOAD: i64 = 16;
+
+const EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "tif", "tiff", "exr"];
+
+///What happens after the last frame
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PlaybackMode {
+    Loop,
+    PingPong,
+    ///Hold the last frame
+    OneShot,
+}
+
+impl PlaybackMode {
+    ///In the order of the mode uniform values
+    pub const ALL: [PlaybackMode; 3] = [
+        PlaybackMode::Loop,
+        PlaybackMode::PingPong,
+        PlaybackMode::OneShot,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlaybackMode::Loop => "loop",
+            PlaybackMode::PingPong => "ping-pong",
+            PlaybackMode::OneShot => "one-shot",
+        }
+    }
+
+    fn index(&self, frame: i64, count: i64) -> i64 {
+        match self {
+            PlaybackMode::Loop => frame.rem_euclid(count),
+            PlaybackMode::PingPong if count == 1 => 0,
+            PlaybackMode::PingPong => {
+                let period = 2 * (count - 1);
+                let frame = frame.rem_euclid(period);
+                if frame < count {
+                    frame
+                } else {
+                    period - frame
+                }
+            }
+            PlaybackMode::OneShot => frame.clamp(0, count - 1),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Playback {
+    pub fps: f32,
+    pub speed: f32,
+    ///Offset into the sequence, from 0 to 1
+    pub position: f32,
+    pub mode: PlaybackMode,
+}
+
+impl Playback {
+    ///Frame number before wrapping, from the graph time so it stays in sync with other nodes
+    fn frame(&self, time: f32, count: usize) -> i64 {
+        (time * self.fps * self.speed + self.position * count as f32).floor() as i64
+    }
+}
+
+///Generation of the frame list, frame index and path
+type Request = (u32, usize, PathBuf);
+type Decoded = (u32, usize, Result<DynamicImage, image::ImageError>);
+
+///Plays a folder of numbered frames. Frames are decoded on a background thread,
+/// the last decoded frame is shown until the current one is ready
+pub struct ImageSequence {
+    frames: Vec<PathBuf>,
+    renderer: ImageRenderer,
+    ///Incremented when the frames change, so old results are ignored
+    generation: u32,
+    requests: Sender<Request>,
+    decoded: Receiver<Decoded>,
+    cache: HashMap<usize, DynamicImage>,
+    pending: HashSet<usize>,
+    shown: Option<usize>,
+}
+
+impl ImageSequence {
+    pub fn new(facade: &impl Facade) -> Result<Self, GlProgramCreationError> {
+        let (requests, decoder_requests) = channel::<Request>();
+        let (decoder_results, decoded) = channel();
+
+        //stops when the sequence is dropped
+        thread::spawn(move || {
+            for (generation, index, path) in decoder_requests {
+                let image = image::open(&path).map(upload_format);
+                if decoder_results.send((generation, index, image)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            frames: vec![],
+            renderer: ImageRenderer::new(facade)?,
+            generation: 0,
+            requests,
+            decoded,
+            cache: HashMap::new(),
+            pending: HashSet::new(),
+            shown: None,
+        })
+    }
+
+    pub fn set_frames(&mut self, frames: Vec<PathBuf>) {
+        self.frames = frames;
+        self.generation += 1;
+        self.cache.clear();
+        self.pending.clear();
+        self.shown = None;
+    }
+
+    pub fn draw(
+        &mut self,
+        facade: &impl Facade,
+        surface: &mut impl Surface,
+        uniforms: &impl Uniforms,
+        time: f32,
+        playback: &Playback,
+    ) -> anyhow::Result<()> {
+        let mut error = None;
+        for (generation, index, image) in self.decoded.try_iter() {
+            if generation != self.generation {
+                continue;
+            }
+
+            self.pending.remove(&index);
+            match image {
+                Ok(image) => {
+                    self.cache.insert(index, image);
+                }
+                Err(err) => error = Some(err),
+            }
+        }
+
+        if !self.frames.is_empty() {
+            let count = self.frames.len() as i64;
+            let frame = playback.frame(time, self.frames.len());
+
+            let upcoming: Vec<usize> = (0..PRELOAD)
+                .map(|ahead| {
+                    let ahead = if playback.speed < 0.0 { -ahead } else { ahead };
+                    playback.mode.index(frame + ahead, count) as usize
+                })
+                .collect();
+
+            self.cache.retain(|index, _| upcoming.contains(index));
+
+            let current = upcoming[0];
+            if self.shown != Some(current) {
+                if let Some(image) = self.cache.remove(&current) {
+                    self.renderer.set_texture(new_image_texture(facade, image)?);
+                    self.shown = Some(current);
+                }
+            }
+
+            for index in &upcoming {
+                let needed = self.shown != Some(*index) && !self.cache.contains_key(index);
+                if needed && self.pending.insert(*index) {
+                    self.requests
+                        .send((self.generation, *index, self.frames[*index].clone()))?;
+                }
+            }
+        }
+
+        self.renderer.draw(surface, uniforms)?;
+
+        match error {
+            Some(err) => Err(err.into()),
+            None => Ok(()),
+        }
+    }
+}
+
+///Convert on the decoding thread, so uploading doesn't need to
+fn upload_format(image: DynamicImage) -> DynamicImage {
+    match image {
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+            DynamicImage::ImageRgba32F(image.into_rgba32f())
+        }
+        image => DynamicImage::ImageRgba8(image.into_rgba8()),
+    }
+}
+
+///Image files in a folder, ordered by the number at the end of their name
+pub fn list_frames(folder: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut frames: Vec<PathBuf> = std::fs::read_dir(folder)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .map(|ext| EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+                .unwrap_or(false)
+        })
+        .collect();
+
+    frames.sort_by_key(|path| (frame_number(path), path.clone()));
+
+    Ok(frames)
+}
+
+fn frame_number(path: &Path) -> Option<u64> {
+    let stem = path.file_stem()?.to_str()?;
+    let digits = stem.len() - stem.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    stem[stem.len() - digits..].parse().ok()
+}
@@ -0,0 +1,62 @@
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::Context;
+use glium::{backend::Facade, Texture2d};
+use isf::ImportedImage;
+
+use crate::image_shader::renderer::new_image_texture;
+
+///An image from the IMPORTED section, bound as a sampler by its key
+pub struct ImportedTexture {
+    pub name: String,
+    path: PathBuf,
+    modified: SystemTime,
+    pub texture: Texture2d,
+}
+
+impl ImportedTexture {
+    ///The image path is relative to the folder of the .fs file
+    pub fn load(
+        facade: &impl Facade,
+        isf_path: &Path,
+        name: &str,
+        imported: &ImportedImage,
+    ) -> anyhow::Result<Self> {
+        let folder = isf_path.parent().unwrap_or_else(|| Path::new(""));
+        let path = folder.join(&imported.path);
+
+        let modified = path.metadata()?.modified()?;
+        let texture = load_texture(facade, &path)?;
+
+        Ok(Self {
+            name: name.to_string(),
+            path,
+            modified,
+            texture,
+        })
+    }
+
+    ///Returns true if the file changed and was loaded again
+    pub fn reload_if_updated(&mut self, facade: &impl Facade) -> anyhow::Result<bool> {
+        let last_modified = self.path.metadata()?.modified()?;
+
+        if self.modified < last_modified {
+            //remember the attempt so a broken file isn't decoded every frame
+            self.modified = last_modified;
+            self.texture = load_texture(facade, &self.path)?;
+            println!("Reloaded imported image: {:?}", self.path);
+
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+}
+
+fn load_texture(facade: &impl Facade, path: &Path) -> anyhow::Result<Texture2d> {
+    let image = image::open(path).with_context(|| format!("Could not open {path:?}"))?;
+    new_image_texture(facade, image)
+}
@@ -10,14 +10,36 @@ use isf::Isf;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+///Where ISF libraries are installed on this platform
 #[cfg(target_os = "windows")]
-pub fn default_isf_path() -> PathBuf {
-    Path::new("C:\\ProgramData\\ISF")
+pub fn default_isf_paths() -> Vec<PathBuf> {
+    vec![Path::new("C:\\ProgramData\\ISF").to_path_buf()]
 }
 
+///Where ISF libraries are installed on this platform
 #[cfg(target_os = "macos")]
-pub fn default_isf_path() -> PathBuf {
-    Path::new("/Library/Graphics/ISF").to_path_buf()
+pub fn default_isf_paths() -> Vec<PathBuf> {
+    vec![Path::new("/Library/Graphics/ISF").to_path_buf()]
+}
+
+///The ISF folder of each XDG data dir, user dir first
+#[cfg(target_os = "linux")]
+pub fn default_isf_paths() -> Vec<PathBuf> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")));
+
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+
+    data_home
+        .into_iter()
+        .chain(data_dirs.split(':').map(PathBuf::from))
+        .map(|dir| dir.join("ISF"))
+        .collect()
 }
 
 #[derive(Error, Debug)]
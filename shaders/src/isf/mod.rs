@@ -1,4 +1,6 @@
+pub mod imported;
 pub mod meta;
+pub mod pass_size;
 pub mod shader;
 pub mod updater;
 
@@ -0,0 +1,349 @@
+use std::{iter::Peekable, str::CharIndices};
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+#[error("Could not parse pass size {text}: {reason}")]
+pub struct PassParseError {
+    pub text: String,
+    pub reason: String,
+}
+
+#[derive(Error, Debug)]
+#[error("Unknown variable ${0} in pass size")]
+pub struct UnknownVariable(pub String);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Func {
+    Floor,
+    Ceil,
+    Round,
+    Abs,
+    Sqrt,
+    Min,
+    Max,
+    Pow,
+    Clamp,
+}
+
+impl Func {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "floor" => Func::Floor,
+            "ceil" => Func::Ceil,
+            "round" => Func::Round,
+            "abs" => Func::Abs,
+            "sqrt" => Func::Sqrt,
+            "min" => Func::Min,
+            "max" => Func::Max,
+            "pow" => Func::Pow,
+            "clamp" => Func::Clamp,
+            _ => return None,
+        })
+    }
+
+    fn arg_count(&self) -> usize {
+        match self {
+            Func::Floor | Func::Ceil | Func::Round | Func::Abs | Func::Sqrt => 1,
+            Func::Min | Func::Max | Func::Pow => 2,
+            Func::Clamp => 3,
+        }
+    }
+
+    fn apply(&self, args: &[f64]) -> f64 {
+        match self {
+            Func::Floor => args[0].floor(),
+            Func::Ceil => args[0].ceil(),
+            Func::Round => args[0].round(),
+            Func::Abs => args[0].abs(),
+            Func::Sqrt => args[0].sqrt(),
+            Func::Min => args[0].min(args[1]),
+            Func::Max => args[0].max(args[1]),
+            Func::Pow => args[0].powf(args[1]),
+            Func::Clamp => args[0].max(args[1]).min(args[2]),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Expr {
+    Num(f64),
+    ///$WIDTH, $HEIGHT or an input name, without the $
+    Var(String),
+    Neg(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(Func, Vec<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, vars: &impl Fn(&str) -> Option<f64>) -> Result<f64, UnknownVariable> {
+        Ok(match self {
+            Expr::Num(num) => *num,
+            Expr::Var(name) => vars(name).ok_or_else(|| UnknownVariable(name.clone()))?,
+            Expr::Neg(expr) => -expr.eval(vars)?,
+            Expr::Binary(op, a, b) => {
+                let (a, b) = (a.eval(vars)?, b.eval(vars)?);
+                match op {
+                    BinaryOp::Add => a + b,
+                    BinaryOp::Sub => a - b,
+                    BinaryOp::Mul => a * b,
+                    BinaryOp::Div => a / b,
+                    BinaryOp::Rem => a % b,
+                }
+            }
+            Expr::Call(func, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.eval(vars))
+                    .collect::<Result<Vec<_>, _>>()?;
+                func.apply(&args)
+            }
+        })
+    }
+}
+
+///A WIDTH or HEIGHT of an ISF pass, like "$WIDTH/2.0" or "max(floor($HEIGHT*$scale), 1.0)"
+#[derive(Clone, Debug, PartialEq)]
+pub struct PassSizeExpr(Expr);
+
+impl PassSizeExpr {
+    pub fn parse(text: &str) -> Result<Self, PassParseError> {
+        let mut parser = Parser {
+            chars: text.char_indices().peekable(),
+            text,
+        };
+
+        let expr = parser.expr();
+        let expr = expr.and_then(|expr| match parser.peek() {
+            None => Ok(expr),
+            Some(c) => Err(format!("unexpected '{c}'")),
+        });
+
+        expr.map(PassSizeExpr).map_err(|reason| PassParseError {
+            text: text.to_string(),
+            reason,
+        })
+    }
+
+    ///Size in pixels, from 1 to max_size. vars gets the value of $WIDTH, $HEIGHT and inputs by name
+    pub fn eval(
+        &self,
+        vars: impl Fn(&str) -> Option<f64>,
+        max_size: u32,
+    ) -> Result<u32, UnknownVariable> {
+        let size = self.0.eval(&vars)?.floor();
+        //NaN casts to 0, so it gets the minimum too
+        Ok((size.clamp(1.0, max_size.max(1) as f64) as u32).max(1))
+    }
+}
+
+///Recursive descent, lowest precedence first
+struct Parser<'a> {
+    chars: Peekable<CharIndices<'a>>,
+    text: &'a str,
+}
+
+type ParseResult = Result<Expr, String>;
+
+impl<'a> Parser<'a> {
+    fn peek(&mut self) -> Option<char> {
+        while let Some((_, c)) = self.chars.peek() {
+            if c.is_whitespace() {
+                self.chars.next();
+            } else {
+                return Some(*c);
+            }
+        }
+        None
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        let found = self.peek() == Some(expected);
+        if found {
+            self.chars.next();
+        }
+        found
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            Err(format!("expected '{expected}'"))
+        }
+    }
+
+    fn expr(&mut self) -> ParseResult {
+        let mut expr = self.term()?;
+        loop {
+            let op = match self.peek() {
+                Some('+') => BinaryOp::Add,
+                Some('-') => BinaryOp::Sub,
+                _ => return Ok(expr),
+            };
+            self.chars.next();
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> ParseResult {
+        let mut expr = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some('*') => BinaryOp::Mul,
+                Some('/') => BinaryOp::Div,
+                Some('%') => BinaryOp::Rem,
+                _ => return Ok(expr),
+            };
+            self.chars.next();
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> ParseResult {
+        if self.eat('-') {
+            Ok(Expr::Neg(Box::new(self.unary()?)))
+        } else if self.eat('+') {
+            self.unary()
+        } else {
+            self.atom()
+        }
+    }
+
+    fn atom(&mut self) -> ParseResult {
+        match self.peek() {
+            Some('(') => {
+                self.chars.next();
+                let expr = self.expr()?;
+                self.expect(')')?;
+                Ok(expr)
+            }
+            Some('$') => {
+                self.chars.next();
+                let name = self.take_while(|c| c.is_alphanumeric() || c == '_');
+                if name.is_empty() {
+                    Err("expected a variable name after '$'".to_string())
+                } else {
+                    Ok(Expr::Var(name.to_string()))
+                }
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let num = self.take_while(|c| c.is_ascii_digit() || c == '.');
+                num.parse()
+                    .map(Expr::Num)
+                    .map_err(|_| format!("invalid number {num}"))
+            }
+            Some(c) if c.is_alphabetic() => {
+                let name = self.take_while(|c| c.is_alphanumeric() || c == '_');
+                let func =
+                    Func::from_name(name).ok_or_else(|| format!("unknown function {name}"))?;
+
+                self.expect('(')?;
+                let mut args = vec![self.expr()?];
+                while self.eat(',') {
+                    args.push(self.expr()?);
+                }
+                self.expect(')')?;
+
+                if args.len() == func.arg_count() {
+                    Ok(Expr::Call(func, args))
+                } else {
+                    Err(format!("{name} takes {} arguments", func.arg_count()))
+                }
+            }
+            Some(c) => Err(format!("unexpected '{c}'")),
+            None => Err("unexpected end".to_string()),
+        }
+    }
+
+    fn take_while(&mut self, pred: impl Fn(char) -> bool) -> &'a str {
+        let start = match self.chars.peek() {
+            Some((i, _)) => *i,
+            None => return "",
+        };
+        let mut end = start;
+        while let Some((i, c)) = self.chars.peek() {
+            if !pred(*c) {
+                break;
+            }
+            end = i + c.len_utf8();
+            self.chars.next();
+        }
+        &self.text[start..end]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(text: &str) -> Result<u32, UnknownVariable> {
+        let vars = |name: &str| match name {
+            "WIDTH" => Some(640.0),
+            "HEIGHT" => Some(480.0),
+            "scale" => Some(0.5),
+            _ => None,
+        };
+        PassSizeExpr::parse(text).unwrap().eval(vars, 4096)
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(eval("1 + 2 * 3").unwrap(), 7);
+        assert_eq!(eval("(1 + 2) * 3").unwrap(), 9);
+        assert_eq!(eval("10 - 4 - 3").unwrap(), 3);
+        assert_eq!(eval("64 / 4 / 2").unwrap(), 8);
+        assert_eq!(eval("2 - -3").unwrap(), 5);
+        assert_eq!(eval("$WIDTH / 2.0").unwrap(), 320);
+        assert_eq!(eval("10 + 17 % 5").unwrap(), 12);
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(eval("max(floor($HEIGHT * $scale), 1.0)").unwrap(), 240);
+        assert_eq!(eval("pow(2, 10)").unwrap(), 1024);
+        assert_eq!(eval("clamp($WIDTH, 1, 100)").unwrap(), 100);
+        assert_eq!(eval("sqrt(abs(-64))").unwrap(), 8);
+    }
+
+    #[test]
+    fn wrong_argument_count() {
+        assert!(PassSizeExpr::parse("floor(1, 2)").is_err());
+        assert!(PassSizeExpr::parse("max(1)").is_err());
+        assert!(PassSizeExpr::parse("clamp(1, 2)").is_err());
+    }
+
+    #[test]
+    fn invalid_syntax() {
+        assert!(PassSizeExpr::parse("").is_err());
+        assert!(PassSizeExpr::parse("$").is_err());
+        assert!(PassSizeExpr::parse("(1 + 2").is_err());
+        assert!(PassSizeExpr::parse("1 2").is_err());
+        assert!(PassSizeExpr::parse("sin(1)").is_err());
+    }
+
+    #[test]
+    fn unknown_variable() {
+        let err = eval("$DEPTH * 2").unwrap_err();
+        assert_eq!(err.0, "DEPTH");
+    }
+
+    #[test]
+    fn out_of_range_is_clamped() {
+        assert_eq!(eval("0").unwrap(), 1);
+        assert_eq!(eval("-$WIDTH").unwrap(), 1);
+        assert_eq!(eval("0 / 0").unwrap(), 1);
+        assert_eq!(eval("sqrt(-1)").unwrap(), 1);
+        assert_eq!(eval("$WIDTH * 100").unwrap(), 4096);
+        assert_eq!(eval("1 / 0").unwrap(), 4096);
+    }
+}
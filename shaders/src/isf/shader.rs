@@ -1,79 +1,201 @@
-use std::{fs::File, io::Read, str::FromStr, time::Instant};
+use std::{fs::File, io::Read, rc::Rc};
 
 use glium::{
     backend::Facade,
+    texture::TextureCreationError,
     uniforms::{AsUniformValue, UniformValue, Uniforms},
     DrawError, Surface, Texture2d,
 };
 use isf::{Isf, Pass};
 
 use crate::{fullscreen_shader::FullscreenFrag, util::GlProgramCreationError};
-use common::texture::{new_texture_2d, DEFAULT_RES};
+use common::{
+    clock::FrameTime,
+    texture::{new_texture_2d_with_format, TextureFormat, DEFAULT_RES},
+};
 use thiserror::Error;
 
-use super::meta::IsfInfo;
+use super::{
+    imported::ImportedTexture,
+    meta::IsfInfo,
+    pass_size::{PassParseError, PassSizeExpr, UnknownVariable},
+};
 
 pub struct IsfShader {
     frag: FullscreenFrag,
     passes: Vec<PassTexture>,
-    // res: (u32, u32),
-    start_inst: Instant,
-    prev_frame_inst: Instant,
-    frame_count: u32,
+    imported: Vec<ImportedTexture>,
+    audio_inputs: Vec<IsfAudioInput>,
+    res: (u32, u32),
+    time_dependent: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum IsfAudioKind {
+    ///The audio input type, samples across and a row per channel
+    Waveform,
+    ///The audioFFT input type, frequency bins across and a row per channel
+    Fft,
+}
+
+///An input the host fills with the current sound
+#[derive(Clone, Debug)]
+pub struct IsfAudioInput {
+    pub name: String,
+    pub kind: IsfAudioKind,
+    ///Samples or bins wanted by the shader
+    pub max: Option<u32>,
+}
+
+impl IsfAudioInput {
+    fn new(input: &isf::Input) -> Option<Self> {
+        let (kind, max) = match &input.ty {
+            isf::InputType::Audio(audio) => (IsfAudioKind::Waveform, audio.max),
+            isf::InputType::AudioFft(audio) => (IsfAudioKind::Fft, audio.max),
+            _ => return None,
+        };
+
+        Some(Self {
+            name: input.name.clone(),
+            kind,
+            max,
+        })
+    }
 }
 
 struct PassTexture {
     pass: Pass,
-    texture: Texture2d,
+    ///None is the output size
+    width: Option<PassSizeExpr>,
+    height: Option<PassSizeExpr>,
+    ///The last result, bound as a uniform by the target name
+    texture: Rc<Texture2d>,
+    ///Rendered into while texture holds the previous frame, for PERSISTENT passes
+    back: Option<Rc<Texture2d>>,
 }
 
 impl Uniforms for PassTexture {
     fn visit_values<'a, F: FnMut(&str, UniformValue<'a>)>(&'a self, mut f: F) {
         if let Some(name) = self.pass.target.as_ref() {
-            f(name, self.texture.as_uniform_value());
+            f(name, self.texture.as_ref().as_uniform_value());
         }
     }
 }
 
-#[derive(Error, Debug)]
-#[error("Could not parse pass size {0}")]
-pub struct PassParseError(String);
+fn parse_size(text: &Option<String>) -> Result<Option<PassSizeExpr>, PassParseError> {
+    text.as_deref().map(PassSizeExpr::parse).transpose()
+}
 
-fn default_or_parse<T: FromStr>(default: T, text: &Option<String>) -> Result<T, PassParseError> {
-    match text {
-        Some(text) => text.parse().map_err(|_| PassParseError(text.clone())),
-        None => Ok(default),
-    }
+///The value of a number, int or bool uniform, so pass sizes can use inputs
+fn uniform_number(uniforms: &impl Uniforms, name: &str) -> Option<f64> {
+    let mut number = None;
+    uniforms.visit_values(|uniform_name, value| {
+        if uniform_name == name {
+            number = match value {
+                UniformValue::Float(value) => Some(value as f64),
+                UniformValue::SignedInt(value) => Some(value as f64),
+                UniformValue::UnsignedInt(value) => Some(value as f64),
+                UniformValue::Bool(value) => Some(value as u8 as f64),
+                _ => None,
+            };
+        }
+    });
+    number
 }
 
-fn calculate_pass_size(
+fn new_pass_texture(
+    facade: &impl Facade,
     pass: &Pass,
-    (width, height): (u32, u32),
-) -> Result<(u32, u32), PassParseError> {
-    Ok((
-        default_or_parse(width, &pass.width)?,
-        default_or_parse(height, &pass.height)?,
-    ))
+    size: (u32, u32),
+) -> Result<Rc<Texture2d>, TextureCreationError> {
+    let format = if pass.float {
+        TextureFormat::Rgba32F
+    } else {
+        TextureFormat::Rgba8
+    };
+
+    let texture = new_texture_2d_with_format(facade, size, format)?;
+    //persistent passes read their last frame, so it can't start as garbage
+    texture.as_surface().clear_color(0.0, 0.0, 0.0, 0.0);
+
+    Ok(Rc::new(texture))
+}
+
+///The largest texture the driver can allocate, which pass sizes are clamped to
+fn max_texture_size(facade: &impl Facade) -> u32 {
+    facade
+        .get_context()
+        .get_capabilities()
+        .max_texture_size
+        .max(1) as u32
 }
 
 impl PassTexture {
+    ///Starts at the output size, until the size expressions are evaluated on draw
     fn new(
         facade: &impl Facade,
         pass: Pass,
         res: (u32, u32),
-    ) -> Result<PassTexture, PassParseError> {
-        let size = calculate_pass_size(&pass, res)?;
-
+    ) -> Result<PassTexture, IsfShaderLoadError> {
         Ok(Self {
+            width: parse_size(&pass.width)?,
+            height: parse_size(&pass.height)?,
+            texture: new_pass_texture(facade, &pass, res)?,
+            back: pass
+                .persistent
+                .then(|| new_pass_texture(facade, &pass, res))
+                .transpose()?,
             pass,
-            texture: new_texture_2d(facade, size).unwrap(),
         })
     }
 
-    pub fn update_size(&mut self, facade: &impl Facade, size: (u32, u32)) {
-        //allow unwrap here as you cannot make a valid PassTexture with an unparsable pass size
-        let size = calculate_pass_size(&self.pass, size).unwrap();
-        self.texture = new_texture_2d(facade, size).unwrap()
+    fn calculate_size(
+        &self,
+        res: (u32, u32),
+        max_size: u32,
+        uniforms: &impl Uniforms,
+    ) -> Result<(u32, u32), UnknownVariable> {
+        let vars = |name: &str| match name {
+            "WIDTH" => Some(res.0 as f64),
+            "HEIGHT" => Some(res.1 as f64),
+            name => uniform_number(uniforms, name),
+        };
+
+        Ok((
+            self.width
+                .as_ref()
+                .map_or(Ok(res.0), |width| width.eval(vars, max_size))?,
+            self.height
+                .as_ref()
+                .map_or(Ok(res.1), |height| height.eval(vars, max_size))?,
+        ))
+    }
+
+    ///Reallocate the buffers if the size changed, losing their contents
+    fn update_size(
+        &mut self,
+        facade: &impl Facade,
+        size: (u32, u32),
+    ) -> Result<(), TextureCreationError> {
+        if self.texture.dimensions() != size {
+            self.texture = new_pass_texture(facade, &self.pass, size)?;
+            if self.back.is_some() {
+                self.back = Some(new_pass_texture(facade, &self.pass, size)?);
+            }
+        }
+        Ok(())
+    }
+
+    ///The texture this pass draws into
+    fn target(&self) -> &Texture2d {
+        self.back.as_deref().unwrap_or(&self.texture)
+    }
+
+    ///Show what was just drawn, keeping the previous frame to draw over next time
+    fn swap(&mut self) {
+        if let Some(back) = &mut self.back {
+            std::mem::swap(&mut self.texture, back);
+        }
     }
 }
 
@@ -82,8 +204,12 @@ impl IsfShader {
         // let source = read_to_string(file).unwrap();
         let mut source = generate_isf_prefix(&isf.def);
         source.push('\n');
-        let mut file = File::open(&isf.path)?;
-        file.read_to_string(&mut source)?;
+        let mut file_source = String::new();
+        File::open(&isf.path)?.read_to_string(&mut file_source)?;
+
+        //the prefix always declares these, so only check the file itself
+        let time_dependent = file_source.contains("TIME") || file_source.contains("FRAMEINDEX");
+        source.push_str(&file_source);
 
         source = source
             .replace("gl_FragColor", "isf_FragColor")
@@ -98,60 +224,138 @@ impl IsfShader {
             .map(|pass| PassTexture::new(facade, pass.clone(), res))
             .collect::<Result<_, _>>()?;
 
-        let now = Instant::now();
-
         // def.passes.first().unwrap().
 
+        let imported = isf
+            .def
+            .imported
+            .iter()
+            .map(|(name, imported)| ImportedTexture::load(facade, &isf.path, name, imported))
+            .collect::<Result<_, _>>()
+            .map_err(IsfShaderLoadError::ImportedImage)?;
+
         Ok(Self {
             frag: FullscreenFrag::new(facade, &source)?,
-            start_inst: now,
-            prev_frame_inst: now,
-            frame_count: 0,
+            time_dependent,
             passes,
-            // res
+            imported,
+            audio_inputs: isf
+                .def
+                .inputs
+                .iter()
+                .filter_map(IsfAudioInput::new)
+                .collect(),
+            res,
         })
     }
 
+    ///Load the IMPORTED images again if they changed on disk. Returns true if any were reloaded
+    pub fn reload_imported_if_updated(&mut self, facade: &impl Facade) -> anyhow::Result<bool> {
+        let mut reloaded = false;
+        for imported in &mut self.imported {
+            reloaded |= imported.reload_if_updated(facade)?;
+        }
+        Ok(reloaded)
+    }
+
+    ///The output size. The pass buffers follow it on the next draw
+    pub fn set_res(&mut self, res: (u32, u32)) {
+        self.res = res;
+    }
+
+    ///Evaluate the pass sizes, reallocating the buffers that changed
+    fn update_pass_sizes(
+        &mut self,
+        facade: &impl Facade,
+        uniforms: &impl Uniforms,
+    ) -> Result<(), IsfDrawError> {
+        let max_size = max_texture_size(facade);
+        for pass in &mut self.passes {
+            let size = pass.calculate_size(self.res, max_size, uniforms)?;
+            pass.update_size(facade, size)?;
+        }
+        Ok(())
+    }
+
+    ///The result of each pass that has a target name
+    pub fn pass_textures(&self) -> impl Iterator<Item = (&str, &Rc<Texture2d>)> {
+        self.passes
+            .iter()
+            .filter_map(|pass| Some((pass.pass.target.as_deref()?, &pass.texture)))
+    }
+
+    ///The audio and audioFFT inputs, which have to be bound when drawing
+    pub fn audio_inputs(&self) -> &[IsfAudioInput] {
+        &self.audio_inputs
+    }
+
+    ///Uses TIME, TIMEDELTA or FRAMEINDEX in the source, listens to audio,
+    /// or has a PERSISTENT pass that builds on its last frame
+    pub fn is_time_dependent(&self) -> bool {
+        self.time_dependent
+            || !self.audio_inputs.is_empty()
+            || self.passes.iter().any(|pass| pass.pass.persistent)
+    }
+
+    fn isf_uniforms<'a, U: Uniforms>(
+        &'a self,
+        inner: &'a U,
+        time: &FrameTime,
+        pass_index: i32,
+    ) -> IsfUniforms<'a, U> {
+        IsfUniforms {
+            inner,
+            time_delta: time.delta,
+            time: time.time,
+            frame_index: time.frame_index,
+            pass_index,
+            passes: &self.passes,
+            imported: &self.imported,
+        }
+    }
+
     pub fn draw(
         &mut self,
+        facade: &impl Facade,
         surface: &mut impl Surface,
         uniforms: &impl Uniforms,
-    ) -> Result<(), DrawError> {
-        let now = Instant::now();
-        let time_delta = now - self.prev_frame_inst;
-        let time_total = now - self.start_inst;
-
-        let mut uniforms = IsfUniforms {
-            inner: uniforms,
-            time_delta: time_delta.as_secs_f32(),
-            time: time_total.as_secs_f32(),
-            frame_index: self.frame_count,
-            pass_index: 0,
-            passes: &self.passes,
-        };
+        time: &FrameTime,
+    ) -> Result<(), IsfDrawError> {
+        self.update_pass_sizes(facade, uniforms)?;
 
         if self.passes.is_empty() {
-            self.frag.draw(surface, &uniforms)?;
-        } else {
-            //TODO: Update dimensions of surfaces
-            // let dimens = surface.get_dimensions();
-            // if dimens != self.res {
-            //     self.res = dimens;
-            //     for pass in &self.passes {
-            //         // pass.update_size(surface, dimens)
-            //     }
-            // }
-
-            let filter = glium::uniforms::MagnifySamplerFilter::Nearest;
-
-            for pass_tex in &self.passes {
-                uniforms.pass_index += 1;
-                self.frag.draw(surface, &uniforms)?;
-                surface.fill(&pass_tex.texture.as_surface(), filter);
+            self.frag
+                .draw(surface, &self.isf_uniforms(uniforms, time, 0))?;
+            return Ok(());
+        }
+
+        let last_index = self.passes.len() - 1;
+
+        //each pass draws into its own target, so later passes can read it
+        for index in 0..self.passes.len() {
+            let pass_tex = &self.passes[index];
+            let pass_uniforms = self.isf_uniforms(uniforms, time, index as i32);
+
+            if pass_tex.pass.target.is_some() {
+                let mut target = pass_tex.target().as_surface();
+                if !pass_tex.pass.persistent {
+                    target.clear_color(0.0, 0.0, 0.0, 0.0);
+                }
+                self.frag.draw(&mut target, &pass_uniforms)?;
+            } else if index == last_index {
+                self.frag.draw(surface, &pass_uniforms)?;
             }
+            //nothing could read an untargeted pass before the last, so it is skipped
+
+            self.passes[index].swap();
         }
 
-        self.frame_count += 1;
+        //a targeted last pass is also the output
+        let last = &self.passes[last_index];
+        if last.pass.target.is_some() {
+            let filter = glium::uniforms::MagnifySamplerFilter::Linear;
+            surface.fill(&last.texture.as_surface(), filter);
+        }
 
         Ok(())
     }
@@ -163,6 +367,7 @@ struct IsfUniforms<'a, U: Uniforms> {
     time: f32,
     pass_index: i32,
     passes: &'a Vec<PassTexture>,
+    imported: &'a Vec<ImportedTexture>,
     inner: &'a U,
 }
 
@@ -175,11 +380,14 @@ impl<U: Uniforms> Uniforms for IsfUniforms<'_, U> {
         f("TIMEDELTA", self.time_delta.as_uniform_value());
         f("TIME", self.time.as_uniform_value());
         f("PASSINDEX", self.pass_index.as_uniform_value());
-        for PassTexture { pass, texture } in self.passes {
+        for PassTexture { pass, texture, .. } in self.passes {
             if let Some(name) = pass.target.as_ref() {
-                f(name, texture.as_uniform_value());
+                f(name, texture.as_ref().as_uniform_value());
             }
         }
+        for imported in self.imported {
+            f(&imported.name, imported.texture.as_uniform_value());
+        }
         self.inner.visit_values(f);
     }
 }
@@ -212,7 +420,9 @@ fn generate_isf_prefix(def: &Isf) -> String {
         .iter()
         .filter_map(|pass| pass.target.as_ref().map(|name| (name, "sampler2D")));
 
-    for (name, gl_ty) in inputs.chain(passes) {
+    let imported = def.imported.keys().map(|name| (name, "sampler2D"));
+
+    for (name, gl_ty) in inputs.chain(passes).chain(imported) {
         prefix.push_str(&format!("uniform {gl_ty} {name};\n"));
     }
 
@@ -221,6 +431,18 @@ fn generate_isf_prefix(def: &Isf) -> String {
     prefix
 }
 
+#[derive(Error, Debug)]
+pub enum IsfDrawError {
+    #[error("Draw error {0}")]
+    Draw(#[from] DrawError),
+
+    #[error("{0}")]
+    PassSize(#[from] UnknownVariable),
+
+    #[error("Pass texture error {0}")]
+    PassTexture(#[from] TextureCreationError),
+}
+
 #[derive(Error, Debug)]
 pub enum IsfShaderLoadError {
     #[error("Load error {0}")]
@@ -231,4 +453,10 @@ pub enum IsfShaderLoadError {
 
     #[error("Parse error {0}")]
     PassParseError(#[from] PassParseError),
+
+    #[error("Pass texture error {0}")]
+    PassTexture(#[from] TextureCreationError),
+
+    #[error("Imported image error {0:#}")]
+    ImportedImage(anyhow::Error),
 }
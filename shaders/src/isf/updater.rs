@@ -30,12 +30,13 @@ pub fn reload_ifs_shader(
 }
 
 impl IsfUpdater {
+    ///Returns true if the shader was reloaded
     pub fn reload_if_updated(
         &mut self,
         facade: &impl Facade,
         isf_info: &mut IsfInfo,
         shader: &mut IsfShader,
-    ) -> Result<(), anyhow::Error> {
+    ) -> Result<bool, anyhow::Error> {
         let new_version = isf_info.path.metadata()?.modified()?;
         let diff = new_version.duration_since(self.modified);
 
@@ -49,9 +50,11 @@ impl IsfUpdater {
                 println!("Reloaded shader: {}", isf_info.name);
                 *shader = new_shader;
                 *isf_info = new_info;
+
+                return Ok(true);
             }
         }
 
-        Ok(())
+        shader.reload_imported_if_updated(facade)
     }
 }
@@ -1,5 +1,7 @@
+pub mod composite;
 pub mod fullscreen_shader;
 pub mod gl_expression;
+pub mod image_shader;
 pub mod isf;
 pub mod obj_shader;
 mod util;
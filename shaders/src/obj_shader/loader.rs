@@ -22,12 +22,13 @@ impl ObjLoader {
         }
     }
 
+    ///Returns true if new data was loaded
     pub fn load_if_changed(
         &mut self,
         facade: &impl Facade,
         path: &Path,
         renderer: &mut ObjRenderer,
-    ) -> Result<(), anyhow::Error> {
+    ) -> Result<bool, anyhow::Error> {
         let last_modified = path.metadata()?.modified()?;
 
         let do_load = match &self.cur_file {
@@ -53,7 +54,7 @@ impl ObjLoader {
             self.cur_file = Some(path.to_path_buf());
         }
 
-        Ok(())
+        Ok(do_load)
     }
 }
 
@@ -1,8 +1,14 @@
 #version 140
 
 in vec3 v_color;
+in vec3 v_normal;
+
 out vec4 out_color;
+out vec4 out_depth;
+out vec4 out_normal;
 
 void main() {
     out_color = vec4(v_color, 1.0);
+    out_depth = vec4(vec3(gl_FragCoord.z), 1.0);
+    out_normal = vec4(normalize(v_normal) * 0.5 + 0.5, 1.0);
 }
\ No newline at end of file
@@ -7,7 +7,7 @@ uniform mat4 model;
 in vec3 position;
 in vec3 normal;
 
-// out vec3 v_normal;
+out vec3 v_normal;
 out vec3 v_color;
 
 void main() {
@@ -15,6 +15,6 @@ void main() {
     vec4 model_norm = model * vec4(normal, 1.0);
     v_color = model_norm.xyz;
     gl_Position = proj_matrix * view * model_pos;
-    // v_normal = 
+    v_normal = (model * vec4(normal, 0.0)).xyz;
     // gl_Position = vec4(position, 1.0);
 }
\ No newline at end of file
@@ -6,13 +6,14 @@ uniform mat4 model;
 
 in vec3 position;
 
-// out vec3 v_normal;
+out vec3 v_normal;
 out vec3 v_color;
 
 void main() {
     vec4 model_pos = model * vec4(position, 1.0);
     v_color = model_pos.xyz;
     gl_Position = proj_matrix * view * model_pos;
-    // v_normal = 
+    //no normals in the mesh, so face the camera
+    v_normal = vec3(0.0, 0.0, 1.0);
     // gl_Position = vec4(position, 1.0);
 }
\ No newline at end of file
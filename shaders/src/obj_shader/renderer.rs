@@ -1,6 +1,4 @@
-use std::time::Instant;
-
-use common::texture::DEFAULT_RES;
+use common::{clock::FrameTime, texture::DEFAULT_RES};
 use genmesh::{
     generators::{IndexedPolygon, SharedVertex},
     Triangulate, Vertices,
@@ -28,12 +26,22 @@ pub fn new_index_buffer<T: glium::index::Index>(
     IndexBuffer::immutable(facade, index::PrimitiveType::TrianglesList, indices).unwrap()
 }
 
+fn projection((width, height): (u32, u32)) -> [[f32; 4]; 4] {
+    glam::Mat4::perspective_rh(
+        std::f32::consts::FRAC_2_PI,
+        width as f32 / height as f32,
+        0.01,
+        100.0,
+    )
+    .to_cols_array_2d()
+}
+
 pub struct ObjRenderer {
     program: Program,
     vert_buffer: VertexBufferAny,
     index_buffer: IndexBufferAny,
     params: DrawParameters<'static>,
-    start: Instant,
+    res: (u32, u32),
     proj_matrix: [[f32; 4]; 4],
 }
 
@@ -84,24 +92,24 @@ impl ObjRenderer {
         )
         .unwrap();
 
-        let proj_matrix = glam::Mat4::perspective_rh(
-            std::f32::consts::FRAC_2_PI,
-            DEFAULT_RES.0 as f32 / DEFAULT_RES.1 as f32,
-            0.01,
-            100.0,
-        )
-        .to_cols_array_2d();
-
         Ok(Self {
             params,
-            start: Instant::now(),
             vert_buffer: new_vertex_buffer(facade, &vertices).into(),
             index_buffer: new_index_buffer(facade, &indices).into(),
             program,
-            proj_matrix,
+            res: DEFAULT_RES,
+            proj_matrix: projection(DEFAULT_RES),
         })
     }
 
+    ///Match the projection to the aspect ratio of the output
+    pub fn set_res(&mut self, res: (u32, u32)) {
+        if res != self.res {
+            self.res = res;
+            self.proj_matrix = projection(res);
+        }
+    }
+
     pub fn update_data(&mut self, facade: &impl Facade, data: Data) {
         match data {
             Data::Pos(verts, indices) => {
@@ -140,11 +148,10 @@ impl ObjRenderer {
         &self,
         surface: &mut impl Surface,
         uniforms: &impl Uniforms,
+        time: &FrameTime,
     ) -> Result<(), DrawError> {
         // let dim = surface.get_dimensions();
-        let time = Instant::now() - self.start;
-
-        let pre_matrix = glam::Mat4::from_rotation_y(time.as_secs_f32() * 0.1);
+        let pre_matrix = glam::Mat4::from_rotation_y(time.time * 0.1);
 
         let view_matrix = glam::Mat4::look_at_rh(
             glam::Vec3::new(0.0, -2.0, 5.0),
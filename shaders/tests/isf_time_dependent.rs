@@ -0,0 +1,27 @@
+use std::path::Path;
+
+use common::headless::new_headless_facade;
+use shaders::isf::{meta::IsfInfo, shader::IsfShader};
+
+fn load(name: &str) -> IsfShader {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../isf_shaders/tests")
+        .join(name);
+    let info = IsfInfo::try_from_path(&path).unwrap();
+
+    let facade = new_headless_facade((64, 64)).unwrap();
+    IsfShader::new(&facade, &info).unwrap()
+}
+
+#[test]
+fn persistent_buffer_is_time_dependent() {
+    //doesn't use TIME, but mixes each frame into the last one
+    let shader = load("Test-PersistentBuffer.fs");
+    assert!(shader.is_time_dependent());
+}
+
+#[test]
+fn plain_filter_is_not_time_dependent() {
+    let shader = load("Test-Float.fs");
+    assert!(!shader.is_time_dependent());
+}
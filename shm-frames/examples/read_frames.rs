@@ -0,0 +1,45 @@
+//! Prints the frames published by the SharedOut node.
+//! `cargo run -p shm-frames --example read_frames [/RustSpout]`
+
+#[cfg(unix)]
+fn main() -> std::io::Result<()> {
+    let name = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| shm_frames::DEFAULT_NAME.to_string());
+
+    let mut reader = shm_frames::ShmReader::open(&name)?;
+    let mut pixels = vec![];
+    let mut last_frame = 0;
+
+    loop {
+        if reader.frame_counter() != last_frame {
+            if let Some(frame) = reader.read_latest(&mut pixels)? {
+                let average = average_color(&pixels);
+                println!(
+                    "frame {} {}x{} average rgba {average:?}",
+                    frame.number, frame.width, frame.height
+                );
+                last_frame = frame.number;
+            }
+        }
+
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+}
+
+#[cfg(unix)]
+fn average_color(pixels: &[u8]) -> [u8; 4] {
+    let count = (pixels.len() / 4).max(1) as u64;
+    let mut sum = [0u64; 4];
+    for pixel in pixels.chunks_exact(4) {
+        for (sum, channel) in sum.iter_mut().zip(pixel) {
+            *sum += *channel as u64;
+        }
+    }
+    sum.map(|sum| (sum / count) as u8)
+}
+
+#[cfg(not(unix))]
+fn main() {
+    eprintln!("Shared memory frames are only supported on unix");
+}
@@ -0,0 +1,68 @@
+//! Share frames between processes through a named POSIX shared memory ring buffer.
+//!
+//! The memory starts with a [Header], followed by [SLOT_COUNT] slots.
+//! Each slot is a [SlotHeader] followed by `slot_capacity` bytes of pixels.
+//! Pixels are RGBA8, rows from top to bottom.
+//!
+//! A writer fills the slot after the latest one, so readers can copy the latest frame while the next is written.
+//! Each slot has a sequence number that is odd while it is being written, so torn reads can be detected.
+
+#![cfg(unix)]
+
+mod mapping;
+pub mod reader;
+pub mod writer;
+
+pub use reader::{Frame, ShmReader};
+pub use writer::ShmWriter;
+
+use std::sync::atomic::{AtomicU32, AtomicU64};
+
+pub const MAGIC: [u8; 4] = *b"RGSH";
+pub const VERSION: u32 = 1;
+pub const SLOT_COUNT: u32 = 3;
+
+///Pixel formats of a frame
+pub const FORMAT_RGBA8: u32 = 1;
+
+///Shared memory name used by a SharedOut node with the default name
+pub const DEFAULT_NAME: &str = "/RustSpout";
+
+#[repr(C)]
+pub struct Header {
+    pub magic: [u8; 4],
+    pub version: u32,
+    pub slot_count: u32,
+    pub format: AtomicU32,
+    ///Bytes of pixels each slot can hold, grows when a bigger frame is written
+    pub slot_capacity: AtomicU64,
+    ///Number of frames written so far
+    pub frame_counter: AtomicU64,
+    ///Slot holding the last complete frame
+    pub latest_slot: AtomicU32,
+    ///Process id of the writer, so a name left behind by a crashed writer can be taken over
+    pub writer_pid: AtomicU32,
+    _padding: [u32; 6],
+}
+
+#[repr(C)]
+pub struct SlotHeader {
+    ///Odd while the slot is being written
+    pub seq: AtomicU64,
+    pub frame: AtomicU64,
+    pub width: AtomicU32,
+    pub height: AtomicU32,
+    _padding: [u32; 2],
+}
+
+pub const HEADER_SIZE: usize = std::mem::size_of::<Header>();
+pub const SLOT_HEADER_SIZE: usize = std::mem::size_of::<SlotHeader>();
+
+///Bytes needed for the header and every slot
+pub fn shm_size(slot_capacity: usize) -> usize {
+    HEADER_SIZE + SLOT_COUNT as usize * (SLOT_HEADER_SIZE + slot_capacity)
+}
+
+fn slot_offset(slot: u32, slot_capacity: usize) -> usize {
+    HEADER_SIZE + slot as usize * (SLOT_HEADER_SIZE + slot_capacity)
+}
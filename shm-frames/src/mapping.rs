@@ -0,0 +1,84 @@
+use std::{ffi::CString, io, os::unix::io::RawFd, ptr::NonNull};
+
+use crate::{slot_offset, Header, SlotHeader, SLOT_HEADER_SIZE};
+
+///create fails if the name already exists, so a writer never resizes memory another writer has mapped
+pub fn shm_open(name: &CString, create: bool) -> io::Result<RawFd> {
+    let flags = if create {
+        libc::O_CREAT | libc::O_EXCL | libc::O_RDWR
+    } else {
+        libc::O_RDONLY
+    };
+
+    let fd = unsafe { libc::shm_open(name.as_ptr(), flags, 0o644) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(fd)
+}
+
+///Size of the shared memory object, which may be bigger than a mapping of it
+pub fn shm_len(fd: RawFd) -> io::Result<usize> {
+    let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+    if unsafe { libc::fstat(fd, &mut stat) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(stat.st_size as usize)
+}
+
+///A shared memory object mapped into this process. Doesn't own the file descriptor
+pub struct Mapping {
+    ptr: NonNull<u8>,
+    len: usize,
+}
+
+impl Mapping {
+    pub fn map(fd: RawFd, len: usize, writable: bool) -> io::Result<Self> {
+        let prot = if writable {
+            libc::PROT_READ | libc::PROT_WRITE
+        } else {
+            libc::PROT_READ
+        };
+
+        let ptr = unsafe { libc::mmap(std::ptr::null_mut(), len, prot, libc::MAP_SHARED, fd, 0) };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Self {
+            ptr: NonNull::new(ptr as *mut u8).expect("mmap returned null"),
+            len,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn header(&self) -> &Header {
+        unsafe { &*(self.ptr.as_ptr() as *const Header) }
+    }
+
+    pub fn slot_header(&self, slot: u32, slot_capacity: usize) -> &SlotHeader {
+        let offset = slot_offset(slot, slot_capacity);
+        unsafe { &*(self.ptr.as_ptr().add(offset) as *const SlotHeader) }
+    }
+
+    ///Pointer to the pixels of a slot. The caller checks that the slot fits in the mapping
+    pub fn slot_pixels(&self, slot: u32, slot_capacity: usize) -> *mut u8 {
+        let offset = slot_offset(slot, slot_capacity) + SLOT_HEADER_SIZE;
+        unsafe { self.ptr.as_ptr().add(offset) }
+    }
+
+    pub fn as_mut_ptr(&self) -> *mut u8 {
+        self.ptr.as_ptr()
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr.as_ptr() as *mut libc::c_void, self.len);
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::{
+    ffi::CString,
+    io,
+    os::unix::io::RawFd,
+    sync::atomic::{fence, Ordering},
+};
+
+use crate::{
+    mapping::{shm_len, shm_open, Mapping},
+    shm_size, HEADER_SIZE, MAGIC, VERSION,
+};
+
+///Times to retry a read that overlapped a write
+const RETRIES: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    ///Counts up from 1 for each frame written
+    pub number: u64,
+}
+
+///Reads frames published by a [crate::ShmWriter], possibly in another process
+pub struct ShmReader {
+    fd: RawFd,
+    mapping: Mapping,
+}
+
+impl ShmReader {
+    pub fn open(name: &str) -> io::Result<Self> {
+        let name =
+            CString::new(name).map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        let fd = shm_open(&name, false)?;
+
+        let reader = shm_len(fd)
+            .and_then(|len| {
+                if len < HEADER_SIZE {
+                    Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Shared memory is too small",
+                    ))
+                } else {
+                    Mapping::map(fd, len, false)
+                }
+            })
+            .map(|mapping| Self { fd, mapping });
+
+        //closed by the reader from here on
+        let reader = match reader {
+            Ok(reader) => reader,
+            Err(err) => {
+                unsafe {
+                    libc::close(fd);
+                }
+                return Err(err);
+            }
+        };
+
+        let header = reader.mapping.header();
+        if header.magic != MAGIC || header.version != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a frame buffer of a compatible version",
+            ));
+        }
+
+        Ok(reader)
+    }
+
+    ///Process id of the writer, which may have exited
+    pub fn writer_pid(&self) -> u32 {
+        self.mapping.header().writer_pid.load(Ordering::Acquire)
+    }
+
+    ///Number of frames written so far, to check for a new frame without copying
+    pub fn frame_counter(&self) -> u64 {
+        self.mapping.header().frame_counter.load(Ordering::Acquire)
+    }
+
+    ///Copy the latest complete frame into pixels. Returns None if nothing has been written yet
+    pub fn read_latest(&mut self, pixels: &mut Vec<u8>) -> io::Result<Option<Frame>> {
+        for _ in 0..RETRIES {
+            let slot_capacity =
+                self.mapping.header().slot_capacity.load(Ordering::Acquire) as usize;
+
+            //the writer grew the buffer
+            if self.mapping.len() < shm_size(slot_capacity) {
+                let len = shm_len(self.fd)?;
+                if len < shm_size(slot_capacity) {
+                    continue;
+                }
+                self.mapping = Mapping::map(self.fd, len, false)?;
+            }
+
+            let header = self.mapping.header();
+            let slot = header.latest_slot.load(Ordering::Acquire);
+            let slot_header = self.mapping.slot_header(slot, slot_capacity);
+
+            let seq = slot_header.seq.load(Ordering::Acquire);
+            if seq == 0 {
+                return Ok(None);
+            }
+            if seq % 2 == 1 {
+                continue;
+            }
+
+            let frame = Frame {
+                width: slot_header.width.load(Ordering::Relaxed),
+                height: slot_header.height.load(Ordering::Relaxed),
+                number: slot_header.frame.load(Ordering::Relaxed),
+            };
+            let len = frame.width as usize * frame.height as usize * 4;
+            if slot_capacity < len {
+                continue;
+            }
+
+            pixels.resize(len, 0);
+            unsafe {
+                std::ptr::copy_nonoverlapping(
+                    self.mapping.slot_pixels(slot, slot_capacity),
+                    pixels.as_mut_ptr(),
+                    len,
+                );
+            }
+
+            //check the slot wasn't written or moved while copying
+            fence(Ordering::Acquire);
+            let unchanged = slot_header.seq.load(Ordering::Relaxed) == seq
+                && header.slot_capacity.load(Ordering::Relaxed) as usize == slot_capacity;
+            if unchanged {
+                return Ok(Some(frame));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl Drop for ShmReader {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
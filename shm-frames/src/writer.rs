@@ -0,0 +1,184 @@
+use std::{
+    ffi::CString,
+    io,
+    os::unix::io::RawFd,
+    sync::atomic::{fence, Ordering},
+};
+
+use crate::{
+    mapping::{shm_open, Mapping},
+    shm_size, Header, ShmReader, FORMAT_RGBA8, MAGIC, SLOT_COUNT, VERSION,
+};
+
+///Publishes frames under a shared memory name, which is removed when dropped
+pub struct ShmWriter {
+    name: CString,
+    fd: RawFd,
+    mapping: Mapping,
+    slot_capacity: usize,
+}
+
+impl ShmWriter {
+    ///name should start with a slash, like /RustSpout.
+    /// Fails with [io::ErrorKind::AlreadyExists] if a running process is writing to the name.
+    /// A name left over by a writer that crashed is taken over
+    pub fn create(name: &str) -> io::Result<Self> {
+        let c_name =
+            CString::new(name).map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+        let fd = match shm_open(&c_name, true) {
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                if !remove_if_abandoned(name, &c_name) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        format!("{name} is already being written to"),
+                    ));
+                }
+                shm_open(&c_name, true)?
+            }
+            result => result?,
+        };
+
+        let mapping = match resize(fd, 0) {
+            Ok(mapping) => mapping,
+            Err(err) => {
+                unsafe {
+                    libc::shm_unlink(c_name.as_ptr());
+                    libc::close(fd);
+                }
+                return Err(err);
+            }
+        };
+
+        unsafe {
+            std::ptr::write(
+                mapping.as_mut_ptr() as *mut Header,
+                Header {
+                    magic: MAGIC,
+                    version: VERSION,
+                    slot_count: SLOT_COUNT,
+                    format: FORMAT_RGBA8.into(),
+                    slot_capacity: 0.into(),
+                    frame_counter: 0.into(),
+                    latest_slot: 0.into(),
+                    writer_pid: std::process::id().into(),
+                    _padding: Default::default(),
+                },
+            );
+        }
+
+        Ok(Self {
+            name: c_name,
+            fd,
+            mapping,
+            slot_capacity: 0,
+        })
+    }
+
+    ///Copy a frame into the slot after the latest one. pixels are RGBA8, rows from top to bottom
+    pub fn write(&mut self, width: u32, height: u32, pixels: &[u8]) -> io::Result<()> {
+        let len = width as usize * height as usize * 4;
+        if pixels.len() != len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Expected {len} bytes for {width}x{height}, got {}",
+                    pixels.len()
+                ),
+            ));
+        }
+
+        if self.slot_capacity < len {
+            self.grow(len)?;
+        }
+
+        let header = self.mapping.header();
+        let slot = (header.latest_slot.load(Ordering::Relaxed) + 1) % SLOT_COUNT;
+        let frame = header.frame_counter.load(Ordering::Relaxed) + 1;
+
+        let slot_header = self.mapping.slot_header(slot, self.slot_capacity);
+        let seq = slot_header.seq.load(Ordering::Relaxed);
+
+        //odd while writing
+        slot_header.seq.store(seq + 1, Ordering::Relaxed);
+        fence(Ordering::Release);
+
+        slot_header.width.store(width, Ordering::Relaxed);
+        slot_header.height.store(height, Ordering::Relaxed);
+        slot_header.frame.store(frame, Ordering::Relaxed);
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                pixels.as_ptr(),
+                self.mapping.slot_pixels(slot, self.slot_capacity),
+                len,
+            );
+        }
+
+        slot_header.seq.store(seq + 2, Ordering::Release);
+        header.latest_slot.store(slot, Ordering::Release);
+        header.frame_counter.store(frame, Ordering::Release);
+
+        Ok(())
+    }
+
+    ///Make room for bigger frames. The slots move, so they are cleared
+    fn grow(&mut self, slot_capacity: usize) -> io::Result<()> {
+        let mapping = resize(self.fd, slot_capacity)?;
+
+        let header = mapping.header();
+        header
+            .slot_capacity
+            .store(slot_capacity as u64, Ordering::Release);
+        for slot in 0..SLOT_COUNT {
+            let slot_header = mapping.slot_header(slot, slot_capacity);
+            slot_header.seq.store(0, Ordering::Release);
+        }
+
+        self.mapping = mapping;
+        self.slot_capacity = slot_capacity;
+
+        Ok(())
+    }
+}
+
+///Unlink the name if the process that wrote to it has exited. Returns true if it is free
+fn remove_if_abandoned(name: &str, c_name: &CString) -> bool {
+    let pid = match ShmReader::open(name) {
+        Ok(reader) => reader.writer_pid(),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return true,
+        //too small for a header, so the writer may still be setting it up
+        Err(_) => return false,
+    };
+
+    if pid == 0 || process_is_alive(pid) {
+        return false;
+    }
+
+    unsafe {
+        libc::shm_unlink(c_name.as_ptr());
+    }
+    true
+}
+
+fn process_is_alive(pid: u32) -> bool {
+    //signal 0 only checks that the process exists, EPERM means it belongs to another user
+    let signalled = unsafe { libc::kill(pid as libc::pid_t, 0) } == 0;
+    signalled || io::Error::last_os_error().raw_os_error() != Some(libc::ESRCH)
+}
+
+fn resize(fd: RawFd, slot_capacity: usize) -> io::Result<Mapping> {
+    let len = shm_size(slot_capacity);
+    if unsafe { libc::ftruncate(fd, len as libc::off_t) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Mapping::map(fd, len, true)
+}
+
+impl Drop for ShmWriter {
+    fn drop(&mut self) {
+        unsafe {
+            libc::shm_unlink(self.name.as_ptr());
+            libc::close(self.fd);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use std::io;
+
+use shm_frames::{Frame, ShmReader, ShmWriter};
+
+///Unique per test and process, so tests can run in parallel
+fn test_name(test: &str) -> String {
+    format!("/shm-frames-test-{}-{test}", std::process::id())
+}
+
+fn pixels(width: u32, height: u32, seed: u8) -> Vec<u8> {
+    (0..width * height * 4)
+        .map(|i| (i as u8).wrapping_add(seed))
+        .collect()
+}
+
+#[test]
+fn reads_what_was_written() {
+    let name = test_name("round-trip");
+    let mut writer = ShmWriter::create(&name).unwrap();
+    let mut reader = ShmReader::open(&name).unwrap();
+    let mut read = vec![];
+
+    assert_eq!(reader.read_latest(&mut read).unwrap(), None);
+
+    let first = pixels(4, 2, 0);
+    writer.write(4, 2, &first).unwrap();
+    assert_eq!(
+        reader.read_latest(&mut read).unwrap(),
+        Some(Frame {
+            width: 4,
+            height: 2,
+            number: 1
+        })
+    );
+    assert_eq!(read, first);
+
+    //bigger frames grow the slots, which the reader has to map again
+    let second = pixels(16, 8, 7);
+    writer.write(16, 8, &second).unwrap();
+    assert_eq!(reader.frame_counter(), 2);
+    assert_eq!(
+        reader.read_latest(&mut read).unwrap(),
+        Some(Frame {
+            width: 16,
+            height: 8,
+            number: 2
+        })
+    );
+    assert_eq!(read, second);
+}
+
+#[test]
+fn rejects_wrong_pixel_count() {
+    let name = test_name("pixel-count");
+    let mut writer = ShmWriter::create(&name).unwrap();
+
+    let err = writer.write(4, 4, &pixels(4, 2, 0)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn second_writer_is_refused() {
+    let name = test_name("second-writer");
+    let mut writer = ShmWriter::create(&name).unwrap();
+    writer.write(8, 8, &pixels(8, 8, 1)).unwrap();
+
+    let err = ShmWriter::create(&name).err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+
+    //the first writer still works, its memory wasn't truncated
+    writer.write(8, 8, &pixels(8, 8, 2)).unwrap();
+    let mut read = vec![];
+    ShmReader::open(&name)
+        .unwrap()
+        .read_latest(&mut read)
+        .unwrap();
+    assert_eq!(read, pixels(8, 8, 2));
+}
+
+#[test]
+fn drop_removes_the_name() {
+    let name = test_name("drop");
+    drop(ShmWriter::create(&name).unwrap());
+
+    assert!(ShmReader::open(&name).is_err());
+    //free to be created again
+    ShmWriter::create(&name).unwrap();
+}
+
+#[test]
+fn name_of_a_dead_writer_is_taken_over() {
+    let name = test_name("dead-writer");
+
+    //leak the writer as if it crashed, then give it the pid of a process that has exited
+    let mut leaked = ShmWriter::create(&name).unwrap();
+    leaked.write(2, 2, &pixels(2, 2, 3)).unwrap();
+    std::mem::forget(leaked);
+
+    let mut child = std::process::Command::new("true").spawn().unwrap();
+    let dead_pid = child.id();
+    child.wait().unwrap();
+    set_writer_pid(&name, dead_pid);
+
+    let mut writer = ShmWriter::create(&name).unwrap();
+    writer.write(4, 4, &pixels(4, 4, 5)).unwrap();
+
+    let mut reader = ShmReader::open(&name).unwrap();
+    assert_eq!(reader.writer_pid(), std::process::id());
+    let mut read = vec![];
+    reader.read_latest(&mut read).unwrap();
+    assert_eq!(read, pixels(4, 4, 5));
+}
+
+fn set_writer_pid(name: &str, pid: u32) {
+    let c_name = std::ffi::CString::new(name).unwrap();
+    unsafe {
+        let fd = libc::shm_open(c_name.as_ptr(), libc::O_RDWR, 0);
+        assert!(fd >= 0);
+        let len = shm_frames::HEADER_SIZE;
+        let ptr = libc::mmap(
+            std::ptr::null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED,
+            fd,
+            0,
+        );
+        assert_ne!(ptr, libc::MAP_FAILED);
+
+        let header = &*(ptr as *const shm_frames::Header);
+        header
+            .writer_pid
+            .store(pid, std::sync::atomic::Ordering::Release);
+
+        libc::munmap(ptr, len);
+        libc::close(fd);
+    }
+}